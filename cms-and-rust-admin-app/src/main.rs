@@ -1,169 +1,635 @@
 use clap::Parser;
 use common::CmsRustConfig;
-use std::sync::Arc;
+use std::{process::ExitCode, sync::Arc, time::Duration};
 
-use axum::routing::{delete, get, post};
+use axum::routing::{delete, get, patch, post, put};
 use axum::{
     debug_handler,
-    extract::{self, Query},
-    Extension, Json, Router,
+    extract::{self, Query, State},
+    middleware,
+    response::IntoResponse,
+    Json, Router,
 };
 use common::{
-    AddPostRequest, AddPostResponse, AppError, Database, DeletePostResponse, GetPostResponse,
+    conditional_get_layer, if_match_holds, last_modified, AddPostRequest, AddPostResponse,
+    AppError, Database, DeletePostResponse, GetPostResponse, PostSeo, PostsPage, PostsQuery,
+    ProbeResponse, Repository, SearchQuery, SearchResults, SecurityHeadersConfig,
+    UpdatePostRequest,
 };
-use http::StatusCode;
-use serde::Deserialize;
+use http::{header, HeaderMap, StatusCode};
+use serde::Serialize;
 
-use tokio::sync::RwLock;
+mod menus;
 
 // TODO : Rename this to something more useful
-type DatabaseT = Arc<RwLock<Database>>;
+type DatabaseT = Arc<dyn Repository>;
 
 #[derive(clap::Parser)]
 struct ProgramArgs {
     // path to the config toml
     #[clap(long, short)]
     config_file: String,
+    // apply pending database migrations before starting
+    #[clap(long)]
+    migrate: bool,
+    #[clap(subcommand)]
+    command: Option<Command>,
 }
 
-// Define default pagination values
-fn default_offset() -> i32 {
-    0
+#[derive(clap::Subcommand)]
+enum Command {
+    // check the config, the image directory and the database, print a
+    // report and exit non-zero on errors, without starting the server
+    CheckConfig,
 }
-fn default_limit() -> i32 {
-    10
-} // Or another sensible default
-
-// Struct for pagination query parameters
-#[derive(Deserialize)]
-struct PaginationParams {
-    #[serde(default = "default_offset")]
-    offset: i32,
-    #[serde(default = "default_limit")]
-    limit: i32,
-}
-
-async fn try_main() -> anyhow::Result<()> {
-    // Read the config
-    let args = ProgramArgs::parse();
-    let config = CmsRustConfig::new(&args.config_file)?;
-
-    let database = Arc::new(RwLock::new(
-        Database::new(&config.database_address, config.database_port).await?,
-    ));
 
+/// All routes of the admin API, served from `database`. Any
+/// `Repository` works, so the API can also run without a database.
+fn admin_router(database: DatabaseT, security_headers: &SecurityHeadersConfig) -> Router {
     // Axum for multiplexing the http connections to endpoints
     // build our application with a route
-    let app = Router::new()
+    let router = Router::new()
         // `GET /` goes to `root`
         .route("/posts", get(get_posts_handler))
         .route("/posts", post(add_post_handler))
+        .route("/posts/search", get(search_posts_handler))
         .route("/posts/:id", get(get_post_handler))
+        .route("/posts/:id", patch(update_post_handler))
         .route("/posts/:id", delete(delete_post_handler))
-        .layer(Extension(database));
+        .route("/menus", get(menus::list_menus_handler))
+        .route("/menus", post(menus::add_menu_handler))
+        .route("/menus/:id", get(menus::get_menu_handler))
+        .route("/menus/:id", delete(menus::delete_menu_handler))
+        .route("/menus/:id/items", post(menus::add_menu_item_handler))
+        .route(
+            "/menus/:id/items/:item_id",
+            put(menus::replace_menu_item_handler),
+        )
+        .route(
+            "/menus/:id/items/:item_id",
+            delete(menus::delete_menu_item_handler),
+        )
+        .route("/health", get(health_handler))
+        .route("/livez", get(common::livez_handler))
+        .route("/readyz", get(readyz_handler))
+        .layer(middleware::from_fn(conditional_get_layer))
+        .with_state(database);
+    let router = common::with_security_headers(router, security_headers);
+
+    common::with_request_tracing(common::with_request_metrics(router))
+}
+
+async fn try_main(args: ProgramArgs) -> anyhow::Result<()> {
+    // Read the config
+    let config = CmsRustConfig::new(&args.config_file)?;
+    common::init_tracing(&config.logging)?;
+    tracing::info!("config:\n{}", config.summary());
+
+    let database = Database::connect(&config).await?;
+    database.check_schema(args.migrate).await?;
+    if config.metrics.enabled {
+        common::spawn_metrics_server(
+            &config.metrics,
+            config.metrics.admin_port,
+            database.clone(),
+            None,
+        )
+        .await?;
+    }
+    let pool = database.clone();
+    let database: DatabaseT = Arc::new(database);
+
+    let app = admin_router(database, &config.security_headers);
+
+    // run our app with hyper, listening on the configured address or socket
+    let tls = common::load_tls(&config).await?;
+    let listener = common::bind_app(
+        &config.admin_bind,
+        config.admin_port,
+        config.admin_socket.as_deref(),
+        // validated when the config was loaded
+        config.socket_mode().unwrap_or(0o660),
+    )
+    .await?;
+    common::serve(
+        listener,
+        app,
+        Duration::from_secs(config.shutdown_delay_secs),
+        Duration::from_secs(config.shutdown_timeout_secs),
+        tls,
+    )
+    .await?;
 
-    // run our app with hyper, listening globally on the configured port
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", config.admin_port))
-        .await
-        .unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Requests are done or dropped, so nothing uses the pool anymore
+    pool.close().await?;
+    tracing::info!("stopped");
 
     Ok(())
 }
 
 #[tokio::main]
-async fn main() {
-    match try_main().await {
+async fn main() -> ExitCode {
+    let args = ProgramArgs::parse();
+
+    if let Some(Command::CheckConfig) = args.command {
+        let report = common::check_config(&args.config_file).await;
+        println!("{}", report);
+        return match report.has_errors() {
+            true => ExitCode::FAILURE,
+            false => ExitCode::SUCCESS,
+        };
+    }
+
+    match try_main(args).await {
+        Ok(()) => ExitCode::SUCCESS,
         Err(e) => {
-            println!("exited program, error: {:?}", e);
+            common::report_fatal(&e);
+            ExitCode::FAILURE
         }
-        _ => {}
     }
 }
 
+#[derive(Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    // newest migration applied to the database
+    schema_version: Option<String>,
+}
+
+#[debug_handler]
+async fn health_handler(
+    State(database): State<DatabaseT>,
+) -> Result<Json<HealthResponse>, AppError> {
+    let schema_version = database.schema_version().await.map_err(|e| AppError {
+        err_msg: format!("database unavailable: {}", e),
+        status_code: StatusCode::SERVICE_UNAVAILABLE,
+    })?;
+
+    Ok(Json(HealthResponse {
+        status: "ok",
+        schema_version,
+    }))
+}
+
+/// `GET /readyz`: the database is reachable with a current schema
+#[debug_handler]
+async fn readyz_handler(
+    State(database): State<DatabaseT>,
+) -> Result<Json<ProbeResponse>, AppError> {
+    common::readiness_response(database.check_ready().await)
+}
+
 #[debug_handler]
 async fn get_posts_handler(
-    Extension(database_lock): Extension<DatabaseT>,
-    Query(pagination): Query<PaginationParams>, // Extract pagination params
-) -> Result<Json<Vec<GetPostResponse>>, AppError> {
-    let database = database_lock.read().await;
-    // Use pagination parameters when calling get_posts
-    let posts = database
-        .get_posts(pagination.offset, pagination.limit)
-        .await?;
+    State(database): State<DatabaseT>,
+    Query(posts_query): Query<PostsQuery>,
+) -> Result<Json<PostsPage>, AppError> {
+    let posts = database.get_posts(&posts_query).await?;
     Ok(Json(posts))
 }
 
+#[debug_handler]
+async fn search_posts_handler(
+    State(database): State<DatabaseT>,
+    Query(search_query): Query<SearchQuery>,
+) -> Result<Json<SearchResults>, AppError> {
+    let results = database.search_posts(&search_query).await?;
+    Ok(Json(results))
+}
+
 #[debug_handler]
 async fn add_post_handler(
-    Extension(database_lock): Extension<DatabaseT>,
+    State(database): State<DatabaseT>,
     extract::Json(post_request): extract::Json<AddPostRequest>,
-) -> Result<Json<AddPostResponse>, Json<AppError>> {
+) -> Result<Json<AddPostResponse>, AppError> {
     // Check that everything is actually populated
     if post_request.title.is_empty() {
         // return some app error
-        return Err(Json(AppError {
+        return Err(AppError {
             err_msg: "cannot have empty post title".into(),
             status_code: StatusCode::BAD_REQUEST,
-        }));
+        });
     }
 
     if post_request.excerpt.is_empty() {
         // return some app error
-        return Err(Json(AppError {
+        return Err(AppError {
             err_msg: "cannot have empty post excerpt".into(),
             status_code: StatusCode::BAD_REQUEST,
-        }));
+        });
     }
 
     if post_request.content.is_empty() {
         // return some app error
-        return Err(Json(AppError {
+        return Err(AppError {
             err_msg: "cannot have empty post content".into(),
             status_code: StatusCode::BAD_REQUEST,
-        }));
+        });
     }
 
-    let database = database_lock.read().await;
+    validate_seo(&post_request.seo)?;
 
-    let post_id = match database
-        .add_post(
-            &post_request.title,
-            &post_request.content,
-            &post_request.excerpt,
-        )
-        .await
-    {
+    let post_id = match database.add_post(&post_request).await {
         Ok(id) => id,
         Err(e) => {
-            return Err(Json(AppError {
+            return Err(AppError {
                 err_msg: format!("could not store post in db: {}", e),
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
-            }))
+            })
         }
     };
 
     Ok(Json(AddPostResponse { post_id }))
 }
 
+/// Longest SEO text, as stored in the VARCHAR(255) columns of `posts`
+const MAX_SEO_FIELD_LEN: usize = 255;
+
+// Check that the SEO links are usable from other sites, and that every
+// field fits its column
+fn validate_seo(seo: &PostSeo) -> Result<(), AppError> {
+    let fields = [
+        ("meta_description", &seo.meta_description),
+        ("og_image", &seo.og_image),
+        ("canonical_url", &seo.canonical_url),
+    ];
+    for (name, value) in fields {
+        if value
+            .as_deref()
+            .is_some_and(|v| v.trim().chars().count() > MAX_SEO_FIELD_LEN)
+        {
+            return Err(AppError {
+                err_msg: format!(
+                    "{} cannot be longer than {} characters",
+                    name, MAX_SEO_FIELD_LEN
+                ),
+                status_code: StatusCode::BAD_REQUEST,
+            });
+        }
+    }
+
+    let is_absolute = |url: &str| url.starts_with("https://") || url.starts_with("http://");
+
+    if let Some(canonical_url) = seo.canonical_url.as_deref().filter(|u| !u.is_empty()) {
+        if !is_absolute(canonical_url) {
+            return Err(AppError {
+                err_msg: "canonical_url must be an absolute http(s) URL".into(),
+                status_code: StatusCode::BAD_REQUEST,
+            });
+        }
+    }
+
+    if let Some(og_image) = seo.og_image.as_deref().filter(|u| !u.is_empty()) {
+        if !is_absolute(og_image) && !og_image.starts_with('/') {
+            return Err(AppError {
+                err_msg: "og_image must be an absolute http(s) URL or a path starting with /"
+                    .into(),
+                status_code: StatusCode::BAD_REQUEST,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[debug_handler]
+async fn update_post_handler(
+    State(database): State<DatabaseT>,
+    extract::Path(post_id): extract::Path<i32>,
+    headers: HeaderMap,
+    extract::Json(update_request): extract::Json<UpdatePostRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    // Fields that are sent must still be populated
+    let required = [
+        ("title", &update_request.title),
+        ("excerpt", &update_request.excerpt),
+        ("content", &update_request.content),
+    ];
+    for (name, value) in required {
+        if value.as_ref().is_some_and(|v| v.is_empty()) {
+            return Err(AppError {
+                err_msg: format!("cannot have empty post {}", name),
+                status_code: StatusCode::BAD_REQUEST,
+            });
+        }
+    }
+
+    if let Some(seo) = &update_request.seo {
+        // only the fields that were sent
+        validate_seo(&seo.apply(&PostSeo::default()))?;
+    }
+
+    let post = database
+        .update_post(post_id, &update_request, &|current| {
+            if_match_holds(&headers, &current.etag())
+        })
+        .await?;
+
+    Ok(post_response(post))
+}
+
 #[debug_handler]
 async fn get_post_handler(
-    Extension(database_lock): Extension<DatabaseT>,
+    State(database): State<DatabaseT>,
     extract::Path(post_id): extract::Path<i32>,
-) -> Result<Json<GetPostResponse>, AppError> {
-    let database = database_lock.read().await;
+) -> Result<impl IntoResponse, AppError> {
     let post = database.get_post(post_id).await?;
 
-    Ok(Json(post))
+    Ok(post_response(post))
+}
+
+// A post along with the validators clients send back in `If-Match`
+// and `If-None-Match`/`If-Modified-Since`
+fn post_response(post: GetPostResponse) -> impl IntoResponse {
+    (
+        [
+            (header::ETAG, post.etag().parse().unwrap()),
+            (header::LAST_MODIFIED, last_modified(post.updated_at)),
+        ],
+        Json(post),
+    )
 }
 
 #[debug_handler]
 async fn delete_post_handler(
-    Extension(database_lock): Extension<DatabaseT>,
+    State(database): State<DatabaseT>,
     extract::Path(post_id): extract::Path<i32>,
 ) -> Result<Json<DeletePostResponse>, AppError> {
-    let database = database_lock.read().await;
     let post = database.delete_post(post_id).await?;
 
     Ok(Json(post))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use common::MemoryRepository;
+    use http::{Method, Request};
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn router() -> Router {
+        admin_router(
+            Arc::new(MemoryRepository::new()),
+            &SecurityHeadersConfig::default(),
+        )
+    }
+
+    fn request(method: Method, uri: &str, body: Option<Value>) -> Request<Body> {
+        let builder = Request::builder().method(method).uri(uri);
+        match body {
+            Some(body) => builder
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    }
+
+    /// Status, headers and JSON body of the router's response
+    async fn send(router: &Router, request: Request<Body>) -> (StatusCode, HeaderMap, Value) {
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+        (status, headers, body)
+    }
+
+    async fn add_post(router: &Router, title: &str) -> i64 {
+        let post = json!({ "title": title, "excerpt": "An excerpt", "content": "Some *text*" });
+        let (status, _, body) = send(router, request(Method::POST, "/posts", Some(post))).await;
+        assert_eq!(status, StatusCode::OK);
+        body["post_id"].as_i64().unwrap()
+    }
+
+    #[tokio::test]
+    async fn posts_can_be_added_read_listed_updated_and_deleted() {
+        let router = router();
+        let post_id = add_post(&router, "First").await;
+        add_post(&router, "Second").await;
+
+        let uri = format!("/posts/{}", post_id);
+        let (status, headers, post) = send(&router, request(Method::GET, &uri, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(post["title"], "First");
+        assert!(headers.contains_key(header::ETAG));
+        assert!(headers.contains_key(header::LAST_MODIFIED));
+
+        let (status, _, page) = send(&router, request(Method::GET, "/posts", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total"], 2);
+        assert_eq!(page["items"].as_array().unwrap().len(), 2);
+
+        let update = json!({ "title": "Renamed" });
+        let (status, _, post) = send(&router, request(Method::PATCH, &uri, Some(update))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(post["title"], "Renamed");
+        assert_eq!(post["excerpt"], "An excerpt");
+
+        let (status, _, deleted) = send(&router, request(Method::DELETE, &uri, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(deleted["post_id"], post_id);
+
+        let (status, _, page) = send(&router, request(Method::GET, "/posts", None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["items"][0]["title"], "Second");
+    }
+
+    #[tokio::test]
+    async fn missing_posts_and_menus_are_not_found() {
+        let router = router();
+        let update = json!({ "title": "Renamed" });
+
+        for request in [
+            request(Method::GET, "/posts/42", None),
+            request(Method::PATCH, "/posts/42", Some(update)),
+            request(Method::DELETE, "/posts/42", None),
+            request(Method::GET, "/menus/7", None),
+            request(Method::DELETE, "/menus/7/items/3", None),
+        ] {
+            let (status, _, error) = send(&router, request).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            assert_eq!(error["status_code"], 404);
+        }
+    }
+
+    #[tokio::test]
+    async fn updates_with_a_stale_if_match_are_refused() {
+        let router = router();
+        let uri = format!("/posts/{}", add_post(&router, "First").await);
+        let (_, headers, _) = send(&router, request(Method::GET, &uri, None)).await;
+        let etag = headers[header::ETAG].to_str().unwrap().to_string();
+
+        let mut update = request(Method::PATCH, &uri, Some(json!({ "title": "Other" })));
+        update
+            .headers_mut()
+            .insert(header::IF_MATCH, "\"not-the-etag\"".parse().unwrap());
+        let (status, _, error) = send(&router, update).await;
+        assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+        assert_eq!(error["status_code"], 412);
+
+        let mut update = request(Method::PATCH, &uri, Some(json!({ "title": "Renamed" })));
+        update
+            .headers_mut()
+            .insert(header::IF_MATCH, etag.parse().unwrap());
+        let (status, _, post) = send(&router, update).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(post["title"], "Renamed");
+
+        // the ETag changed along with the post
+        let mut update = request(Method::PATCH, &uri, Some(json!({ "title": "Other" })));
+        update
+            .headers_mut()
+            .insert(header::IF_MATCH, etag.parse().unwrap());
+        let (status, _, _) = send(&router, update).await;
+        assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+        let (_, _, post) = send(&router, request(Method::GET, &uri, None)).await;
+        assert_eq!(post["title"], "Renamed");
+    }
+
+    #[tokio::test]
+    async fn empty_fields_are_rejected() {
+        let router = router();
+
+        for field in ["title", "excerpt", "content"] {
+            let mut post = json!({ "title": "A", "excerpt": "B", "content": "C" });
+            post[field] = json!("");
+            let (status, _, error) =
+                send(&router, request(Method::POST, "/posts", Some(post))).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(
+                error["err_msg"],
+                format!("cannot have empty post {}", field)
+            );
+        }
+
+        let uri = format!("/posts/{}", add_post(&router, "First").await);
+        let update = json!({ "content": "" });
+        let (status, _, error) = send(&router, request(Method::PATCH, &uri, Some(update))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["err_msg"], "cannot have empty post content");
+
+        let (status, _, _) = send(
+            &router,
+            request(Method::POST, "/menus", Some(json!({ "name": " " }))),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, _, page) = send(&router, request(Method::GET, "/posts", None)).await;
+        assert_eq!(page["total"], 1);
+    }
+
+    #[tokio::test]
+    async fn seo_updates_keep_the_fields_left_out() {
+        let router = router();
+        let seo = json!({
+            "meta_description": "About the post",
+            "og_image": "/cover.png",
+            "canonical_url": "https://example.com/first",
+            "noindex": true,
+        });
+        let post = json!({ "title": "First", "excerpt": "B", "content": "C", "seo": seo });
+        let (_, _, added) = send(&router, request(Method::POST, "/posts", Some(post))).await;
+        let uri = format!("/posts/{}", added["post_id"]);
+
+        let update = json!({ "seo": { "meta_description": "Updated" } });
+        let (status, _, post) = send(&router, request(Method::PATCH, &uri, Some(update))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(post["seo"]["meta_description"], "Updated");
+        assert_eq!(post["seo"]["og_image"], "/cover.png");
+        assert_eq!(post["seo"]["canonical_url"], "https://example.com/first");
+        assert_eq!(post["seo"]["noindex"], true);
+
+        let update = json!({ "seo": { "og_image": null, "noindex": false } });
+        let (_, _, post) = send(&router, request(Method::PATCH, &uri, Some(update))).await;
+        assert_eq!(post["seo"]["meta_description"], "Updated");
+        assert_eq!(post["seo"]["og_image"], Value::Null);
+        assert_eq!(post["seo"]["noindex"], false);
+    }
+
+    #[tokio::test]
+    async fn seo_fields_longer_than_their_column_are_rejected() {
+        let router = router();
+        let too_long = "a".repeat(MAX_SEO_FIELD_LEN + 1);
+
+        let post = json!({
+            "title": "A",
+            "excerpt": "B",
+            "content": "C",
+            "seo": { "meta_description": too_long },
+        });
+        let (status, _, error) = send(&router, request(Method::POST, "/posts", Some(post))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            error["err_msg"],
+            "meta_description cannot be longer than 255 characters"
+        );
+
+        let uri = format!("/posts/{}", add_post(&router, "First").await);
+        let update = json!({ "seo": { "og_image": format!("/{}", too_long) } });
+        let (status, _, _) = send(&router, request(Method::PATCH, &uri, Some(update))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, _, page) = send(&router, request(Method::GET, "/posts", None)).await;
+        assert_eq!(page["total"], 1);
+    }
+
+    #[tokio::test]
+    async fn menu_items_are_nested_below_their_parent() {
+        let router = router();
+        let post_id = add_post(&router, "About").await;
+
+        let menu = json!({ "name": "main" });
+        let (status, _, menu) = send(&router, request(Method::POST, "/menus", Some(menu))).await;
+        assert_eq!(status, StatusCode::OK);
+        let items_uri = format!("/menus/{}/items", menu["menu_id"]);
+
+        let heading = json!({ "label": "Company" });
+        let (_, _, heading) = send(&router, request(Method::POST, &items_uri, Some(heading))).await;
+        let about =
+            json!({ "label": "About", "parent_id": heading["item_id"], "post_id": post_id });
+        let (status, _, _) = send(&router, request(Method::POST, &items_uri, Some(about))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let uri = format!("/menus/{}", menu["menu_id"]);
+        let (status, _, menu) = send(&router, request(Method::GET, &uri, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(menu["items"][0]["label"], "Company");
+        assert_eq!(menu["items"][0]["children"][0]["label"], "About");
+        assert_eq!(menu["items"][0]["children"][0]["post_id"], post_id);
+
+        let (_, _, menus) = send(&router, request(Method::GET, "/menus", None)).await;
+        assert_eq!(menus[0]["name"], "main");
+    }
+
+    #[tokio::test]
+    async fn menu_item_fields_longer_than_their_column_are_rejected() {
+        let router = router();
+        let too_long = "a".repeat(256);
+
+        let menu = json!({ "name": "main" });
+        let (_, _, menu) = send(&router, request(Method::POST, "/menus", Some(menu))).await;
+        let items_uri = format!("/menus/{}/items", menu["menu_id"]);
+
+        for item in [
+            json!({ "label": too_long }),
+            json!({ "label": "About", "title": too_long }),
+            json!({ "label": "About", "url": format!("/{}", too_long) }),
+        ] {
+            let (status, _, error) =
+                send(&router, request(Method::POST, &items_uri, Some(item))).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(error["status_code"], 400);
+        }
+
+        let uri = format!("/menus/{}", menu["menu_id"]);
+        let (_, _, menu) = send(&router, request(Method::GET, &uri, None)).await;
+        assert_eq!(menu["items"], json!([]));
+    }
+}
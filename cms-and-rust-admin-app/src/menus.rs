@@ -0,0 +1,77 @@
+use axum::{
+    debug_handler,
+    extract::{self, State},
+    Json,
+};
+use common::{
+    AddMenuItemResponse, AddMenuRequest, AddMenuResponse, AppError, DeleteMenuItemResponse,
+    DeleteMenuResponse, MenuItemRequest, MenuResponse, MenuSummary,
+};
+
+use crate::DatabaseT;
+
+#[debug_handler]
+pub async fn list_menus_handler(
+    State(database): State<DatabaseT>,
+) -> Result<Json<Vec<MenuSummary>>, AppError> {
+    let menus = database.list_menus().await?;
+    Ok(Json(menus))
+}
+
+#[debug_handler]
+pub async fn add_menu_handler(
+    State(database): State<DatabaseT>,
+    extract::Json(menu_request): extract::Json<AddMenuRequest>,
+) -> Result<Json<AddMenuResponse>, AppError> {
+    let menu_id = database.add_menu(&menu_request).await?;
+    Ok(Json(AddMenuResponse { menu_id }))
+}
+
+#[debug_handler]
+pub async fn get_menu_handler(
+    State(database): State<DatabaseT>,
+    extract::Path(menu_id): extract::Path<i32>,
+) -> Result<Json<MenuResponse>, AppError> {
+    let menu = database.get_menu(menu_id).await?;
+    Ok(Json(menu))
+}
+
+#[debug_handler]
+pub async fn delete_menu_handler(
+    State(database): State<DatabaseT>,
+    extract::Path(menu_id): extract::Path<i32>,
+) -> Result<Json<DeleteMenuResponse>, AppError> {
+    let menu = database.delete_menu(menu_id).await?;
+    Ok(Json(menu))
+}
+
+#[debug_handler]
+pub async fn add_menu_item_handler(
+    State(database): State<DatabaseT>,
+    extract::Path(menu_id): extract::Path<i32>,
+    extract::Json(item_request): extract::Json<MenuItemRequest>,
+) -> Result<Json<AddMenuItemResponse>, AppError> {
+    let item_id = database.add_menu_item(menu_id, &item_request).await?;
+    Ok(Json(AddMenuItemResponse { item_id }))
+}
+
+#[debug_handler]
+pub async fn replace_menu_item_handler(
+    State(database): State<DatabaseT>,
+    extract::Path((menu_id, item_id)): extract::Path<(i32, i32)>,
+    extract::Json(item_request): extract::Json<MenuItemRequest>,
+) -> Result<Json<MenuResponse>, AppError> {
+    let menu = database
+        .replace_menu_item(menu_id, item_id, &item_request)
+        .await?;
+    Ok(Json(menu))
+}
+
+#[debug_handler]
+pub async fn delete_menu_item_handler(
+    State(database): State<DatabaseT>,
+    extract::Path((menu_id, item_id)): extract::Path<(i32, i32)>,
+) -> Result<Json<DeleteMenuItemResponse>, AppError> {
+    let item = database.delete_menu_item(menu_id, item_id).await?;
+    Ok(Json(item))
+}
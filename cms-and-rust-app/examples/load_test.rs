@@ -0,0 +1,175 @@
+//! Load test for the public site. It keeps `concurrency` keep-alive
+//! connections busy with GET requests for a fixed time, then reports the
+//! throughput and latency percentiles. Run it against two builds of the
+//! app, with the same database and config, to compare them:
+//!
+//! ```bash
+//! cargo run --release --example load_test -- --url http://127.0.0.1:8080/ --concurrency 64
+//! ```
+//!
+//! Point it at `/search?q=...` or set `cache_enabled = false` to measure
+//! the database path rather than the page cache.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use clap::Parser;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+#[derive(clap::Parser)]
+struct LoadTestArgs {
+    // page to request, plain http only
+    #[clap(long, default_value = "http://127.0.0.1:8080/")]
+    url: String,
+    // number of connections sending requests at the same time
+    #[clap(long, default_value_t = 64)]
+    concurrency: usize,
+    // how long to keep sending requests
+    #[clap(long, default_value_t = 10)]
+    duration_secs: u64,
+}
+
+#[derive(Default)]
+struct WorkerStats {
+    latencies: Vec<Duration>,
+    failed: u64,
+}
+
+/// Split `http://host:port/path` into the address to connect to, the
+/// `Host` header and the request target
+fn parse_url(url: &str) -> anyhow::Result<(String, String, String)> {
+    let rest = url
+        .strip_prefix("http://")
+        .context("only http:// URLs are supported")?;
+    let (host, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+
+    Ok((address, host.to_string(), path.to_string()))
+}
+
+/// Send one request on the connection and read the whole response,
+/// returning whether it was a `200 OK`
+async fn request(
+    stream: &mut BufReader<TcpStream>,
+    host: &str,
+    path: &str,
+) -> anyhow::Result<bool> {
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, host);
+    stream.get_mut().write_all(request.as_bytes()).await?;
+
+    let mut status_line = String::new();
+    stream.read_line(&mut status_line).await?;
+    let ok = status_line.split(' ').nth(1) == Some("200");
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            anyhow::bail!("connection closed mid-response");
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    stream.read_exact(&mut body).await?;
+
+    Ok(ok)
+}
+
+async fn worker(address: String, host: String, path: String, deadline: Instant) -> WorkerStats {
+    let mut stats = WorkerStats::default();
+
+    while Instant::now() < deadline {
+        let mut stream = match TcpStream::connect(&address).await {
+            Ok(stream) => BufReader::new(stream),
+            Err(_) => {
+                stats.failed += 1;
+                continue;
+            }
+        };
+
+        // Reuse the connection until it fails, like a browser would
+        while Instant::now() < deadline {
+            let started = Instant::now();
+            match request(&mut stream, &host, &path).await {
+                Ok(true) => stats.latencies.push(started.elapsed()),
+                Ok(false) => stats.failed += 1,
+                Err(_) => {
+                    stats.failed += 1;
+                    break;
+                }
+            }
+        }
+    }
+
+    stats
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = LoadTestArgs::parse();
+    let (address, host, path) = parse_url(&args.url)?;
+    let duration = Duration::from_secs(args.duration_secs);
+    let deadline = Instant::now() + duration;
+
+    let workers: Vec<_> = (0..args.concurrency.max(1))
+        .map(|_| {
+            tokio::spawn(worker(
+                address.clone(),
+                host.clone(),
+                path.clone(),
+                deadline,
+            ))
+        })
+        .collect();
+
+    let mut latencies = Vec::new();
+    let mut failed = 0;
+    for worker in workers {
+        let stats = worker.await?;
+        latencies.extend(stats.latencies);
+        failed += stats.failed;
+    }
+    latencies.sort();
+
+    let percentile = |p: f64| {
+        latencies
+            .get(((latencies.len() as f64 * p) as usize).min(latencies.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or_default()
+    };
+
+    println!("url:         {}", args.url);
+    println!("concurrency: {}", args.concurrency);
+    println!("requests:    {} ok, {} failed", latencies.len(), failed);
+    println!(
+        "throughput:  {:.0} req/s",
+        latencies.len() as f64 / duration.as_secs_f64()
+    );
+    println!(
+        "latency:     p50 {:?}, p99 {:?}, max {:?}",
+        percentile(0.50),
+        percentile(0.99),
+        latencies.last().copied().unwrap_or_default()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use std::{sync::Arc, time::Duration};
+
+use common::{CacheScope, PageCache};
+
+use crate::{DatabaseT, SharedConfig};
+
+/// Evict the cached pages that show content from `scope`
+fn invalidate(cache: &PageCache, scope: CacheScope) {
+    match scope {
+        CacheScope::Post(post_id) => cache.invalidate_path(&format!("/post/{}", post_id)),
+        CacheScope::Listing => {
+            cache.invalidate_path("/");
+            cache.invalidate_prefix("/sitemap");
+        }
+        CacheScope::Feed => {
+            cache.invalidate_path("/feed.xml");
+            cache.invalidate_path("/atom.xml");
+        }
+        CacheScope::Menu => cache.clear(),
+    }
+}
+
+/// Poll the `cache_generation` table for writes made by the admin app,
+/// and evict the cached pages they made stale. The interval is read from
+/// `config` on every round, and rounds are skipped while the cache is
+/// turned off.
+pub fn spawn_poller(database: DatabaseT, cache: Arc<PageCache>, config: SharedConfig) {
+    tokio::spawn(async move {
+        let mut generation = match database.latest_cache_generation().await {
+            Ok(generation) => generation,
+            Err(e) => {
+                // Start from scratch, which evicts everything once
+                tracing::warn!("could not read the cache generation: {:#}", e);
+                0
+            }
+        };
+
+        loop {
+            let interval = config.load().cache.poll_interval_ms.max(1);
+            tokio::time::sleep(Duration::from_millis(interval)).await;
+            if !cache.is_enabled() {
+                continue;
+            }
+
+            match database.stale_cache_scopes(generation).await {
+                Ok(stale) => {
+                    for (scope, scope_generation) in stale {
+                        invalidate(&cache, scope);
+                        generation = generation.max(scope_generation);
+                    }
+                }
+                Err(e) => tracing::warn!("could not poll for cache invalidations: {:#}", e),
+            }
+        }
+    });
+}
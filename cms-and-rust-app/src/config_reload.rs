@@ -0,0 +1,80 @@
+use std::{fs, sync::Arc, time::Duration, time::SystemTime};
+
+use common::{CmsRustConfig, PageCache};
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::SharedConfig;
+
+/// How often the config file is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Modification time and size of the file, which change whenever it is
+/// written or replaced. `None` while it can't be read.
+fn file_stamp(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Load the config file again and swap in the parts that can change
+/// without a restart. A file that fails to load or validate leaves the
+/// current config in place.
+fn reload(path: &str, config: &SharedConfig, cache: &PageCache) {
+    let new = match CmsRustConfig::new(path) {
+        Ok(new) => new,
+        Err(e) => {
+            tracing::error!(
+                "could not reload the config, keeping the current one: {:#}",
+                e
+            );
+            return;
+        }
+    };
+
+    let (reloaded, ignored) = config.load().reload_from(new);
+    for key in ignored {
+        tracing::warn!(
+            "config key `{}` changed, the new value takes effect on restart",
+            key
+        );
+    }
+
+    // Cached pages show the old navbar and site metadata, so they all go
+    cache.reconfigure(reloaded.cache_enabled, &reloaded.cache);
+    config.store(Arc::new(reloaded));
+    tracing::info!("reloaded the config from {}", path);
+}
+
+/// Reload the config whenever the file at `path` changes, and on SIGHUP
+pub fn spawn_reloader(path: String, config: SharedConfig, cache: Arc<PageCache>) {
+    tokio::spawn(async move {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => Some(hangup),
+            Err(e) => {
+                tracing::warn!("could not listen for SIGHUP: {}", e);
+                None
+            }
+        };
+        let mut stamp = file_stamp(&path);
+        let mut ticker = tokio::time::interval(WATCH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let current = file_stamp(&path);
+                    // A missing file is most likely being replaced, wait
+                    // for the new one rather than reporting an error
+                    if current.is_none() || current == stamp {
+                        continue;
+                    }
+                    stamp = current;
+                }
+                Some(_) = async { hangup.as_mut()?.recv().await } => {
+                    tracing::info!("received SIGHUP, reloading the config");
+                    stamp = file_stamp(&path);
+                }
+            }
+
+            reload(&path, &config, &cache);
+        }
+    });
+}
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    debug_handler,
+    extract::State,
+    http::{header, StatusCode},
+    response::Response,
+};
+use common::{
+    last_modified, markdown_to_html, AppError, CmsRustConfig, GetPostResponse, PostsQuery,
+    MAX_POSTS_PER_PAGE,
+};
+
+use crate::DatabaseT;
+
+/// Escape text for use in XML character data and attribute values
+pub(crate) fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Build the feed response. Its ETag and the answers to conditional
+/// requests are handled by `conditional_get_layer`.
+fn feed_response(content_type: &str, posts: &[GetPostResponse], body: String) -> Response {
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CACHE_CONTROL, "public, max-age=300");
+
+    if let Some(updated) = posts.iter().map(|post| post.updated_at).max() {
+        builder = builder.header(header::LAST_MODIFIED, last_modified(updated));
+    }
+
+    builder.body(Body::from(body)).unwrap()
+}
+
+async fn latest_posts(
+    database: &DatabaseT,
+    config: &CmsRustConfig,
+) -> Result<Vec<GetPostResponse>, AppError> {
+    let posts_page = database
+        .get_posts(&PostsQuery {
+            per_page: config.feed_size.clamp(1, MAX_POSTS_PER_PAGE),
+            ..Default::default()
+        })
+        .await?;
+
+    Ok(posts_page.items)
+}
+
+fn rss_feed(config: &CmsRustConfig, posts: &[GetPostResponse]) -> String {
+    let site_link = xml_escape(&config.absolute_url("/"));
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n");
+    xml.push_str(&format!(
+        "<title>{}</title>\n",
+        xml_escape(&config.site_title)
+    ));
+    xml.push_str(&format!("<link>{}</link>\n", site_link));
+    xml.push_str(&format!(
+        "<description>Latest posts from {}</description>\n",
+        xml_escape(&config.site_title)
+    ));
+    xml.push_str(&format!(
+        "<atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+        xml_escape(&config.absolute_url("/feed.xml"))
+    ));
+    if let Some(last_build) = posts.iter().map(|post| post.updated_at).max() {
+        xml.push_str(&format!(
+            "<lastBuildDate>{}</lastBuildDate>\n",
+            last_build.to_rfc2822()
+        ));
+    }
+
+    for post in posts {
+        let link = xml_escape(&config.absolute_url(&format!("/post/{}", post.post_id)));
+        xml.push_str("<item>\n");
+        xml.push_str(&format!("<title>{}</title>\n", xml_escape(&post.title)));
+        xml.push_str(&format!("<link>{}</link>\n", link));
+        xml.push_str(&format!("<guid isPermaLink=\"true\">{}</guid>\n", link));
+        xml.push_str(&format!(
+            "<pubDate>{}</pubDate>\n",
+            post.created_at.to_rfc2822()
+        ));
+        xml.push_str(&format!(
+            "<description>{}</description>\n",
+            xml_escape(&markdown_to_html(&post.content))
+        ));
+        xml.push_str("</item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+fn atom_feed(config: &CmsRustConfig, posts: &[GetPostResponse]) -> String {
+    let site_link = xml_escape(&config.absolute_url("/"));
+    let updated = posts
+        .iter()
+        .map(|post| post.updated_at)
+        .max()
+        .unwrap_or_default();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!(
+        "<title>{}</title>\n",
+        xml_escape(&config.site_title)
+    ));
+    xml.push_str(&format!("<id>{}</id>\n", site_link));
+    xml.push_str(&format!(
+        "<link href=\"{}\" rel=\"self\" type=\"application/atom+xml\"/>\n",
+        xml_escape(&config.absolute_url("/atom.xml"))
+    ));
+    xml.push_str(&format!(
+        "<link href=\"{}\" rel=\"alternate\"/>\n",
+        site_link
+    ));
+    xml.push_str(&format!("<updated>{}</updated>\n", updated.to_rfc3339()));
+    xml.push_str(&format!(
+        "<author><name>{}</name></author>\n",
+        xml_escape(&config.site_title)
+    ));
+
+    for post in posts {
+        let link = xml_escape(&config.absolute_url(&format!("/post/{}", post.post_id)));
+        xml.push_str("<entry>\n");
+        xml.push_str(&format!("<title>{}</title>\n", xml_escape(&post.title)));
+        xml.push_str(&format!("<id>{}</id>\n", link));
+        xml.push_str(&format!("<link href=\"{}\" rel=\"alternate\"/>\n", link));
+        xml.push_str(&format!(
+            "<published>{}</published>\n",
+            post.created_at.to_rfc3339()
+        ));
+        xml.push_str(&format!(
+            "<updated>{}</updated>\n",
+            post.updated_at.to_rfc3339()
+        ));
+        xml.push_str(&format!(
+            "<summary>{}</summary>\n",
+            xml_escape(&post.excerpt)
+        ));
+        xml.push_str(&format!(
+            "<content type=\"html\">{}</content>\n",
+            xml_escape(&markdown_to_html(&post.content))
+        ));
+        xml.push_str("</entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+#[debug_handler(state = crate::AppState)]
+pub async fn rss_handler(
+    State(database): State<DatabaseT>,
+    State(config): State<Arc<CmsRustConfig>>,
+) -> Result<Response, AppError> {
+    let posts = latest_posts(&database, &config).await?;
+
+    Ok(feed_response(
+        "application/rss+xml; charset=utf-8",
+        &posts,
+        rss_feed(&config, &posts),
+    ))
+}
+
+#[debug_handler(state = crate::AppState)]
+pub async fn atom_handler(
+    State(database): State<DatabaseT>,
+    State(config): State<Arc<CmsRustConfig>>,
+) -> Result<Response, AppError> {
+    let posts = latest_posts(&database, &config).await?;
+
+    Ok(feed_response(
+        "application/atom+xml; charset=utf-8",
+        &posts,
+        atom_feed(&config, &posts),
+    ))
+}
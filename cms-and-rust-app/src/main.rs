@@ -1,41 +1,82 @@
+use anyhow::Context;
+use arc_swap::ArcSwap;
 use clap::Parser;
 // Remove direct CmsRustConfig import, it's handled in the block below
 use std::{
     fs::File,
     io::{self, Read}, // Add io
     // Remove std::path::Path import to avoid conflict
+    process::ExitCode,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use axum::{
     debug_handler,
-    extract::{Path, Query}, // Keep only needed extractors, remove self
+    extract::{FromRef, Path, Query, State}, // Keep only needed extractors, remove self
     http::StatusCode,
+    middleware,
     response::Html,
     routing::get,
-    Extension,
     Json,
     Router,
 };
 use common::{
-    markdown_filter,
-    AppError,
-    CmsRustConfig,
-    Database,
-    GetPostResponse, // Ensure CmsRustConfig is only here
+    conditional_get_layer, markdown_filter, page_cache_layer, AppError, CmsRustConfig, CspNonce,
+    Database, MenuLink, PageCache, PostsQuery, ProbeResponse, Repository, SearchQuery,
 };
-use minijinja::{context, Environment};
-use serde::Deserialize;
-use tokio::sync::RwLock;
+use minijinja::{context, AutoEscape, Environment};
+use serde::{Deserialize, Serialize};
+
+mod cache_invalidation;
+mod config_reload;
+mod feeds;
+mod seo;
+mod sitemap;
+
+use seo::SeoContext;
 
 // TODO : Rename this to something more useful
-type DatabaseT = Arc<RwLock<Database>>;
+type DatabaseT = Arc<dyn Repository>;
+
+/// The current config, swapped out as a whole when the file is reloaded
+type SharedConfig = Arc<ArcSwap<CmsRustConfig>>;
+
+/// State shared by the handlers, each of them taking the parts it
+/// needs through `State`
+#[derive(Clone, FromRef)]
+pub(crate) struct AppState {
+    database: DatabaseT,
+    #[from_ref(skip)]
+    config: SharedConfig,
+    views: Views,
+}
+
+/// Handlers get a snapshot of the config, which stays the same for the
+/// whole request even when a reload happens meanwhile
+impl FromRef<AppState> for Arc<CmsRustConfig> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.load_full()
+    }
+}
 
 #[derive(clap::Parser)]
 struct ProgramArgs {
     // path to the config toml
     #[clap(long, short)]
     config_file: String,
+    // apply pending database migrations before starting
+    #[clap(long)]
+    migrate: bool,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    // check the config, the image directory and the database, print a
+    // report and exit non-zero on errors, without starting the server
+    CheckConfig,
 }
 
 // Update read_file signature to accept AsRef<Path> and return io::Result
@@ -46,96 +87,270 @@ fn read_file<P: AsRef<std::path::Path>>(path: P) -> io::Result<String> {
     Ok(file_contents)
 }
 
-async fn try_main() -> anyhow::Result<()> {
-    // Read the config
-    let args = ProgramArgs::parse();
-    let config = Arc::new(CmsRustConfig::new(&args.config_file)?);
-
-    // TODO : we probably don't want to print secrets ;)
-    println!("config");
-    println!("{:#?}", config);
+/// Template environment shared by the page handlers. The templates are
+/// named without an `.html` extension, so HTML escaping is turned on
+/// explicitly to keep request input such as the search query from
+/// injecting markup.
+fn template_env() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_auto_escape_callback(|_| AutoEscape::Html);
+    env.add_filter("markdown", markdown_filter);
+    env
+}
 
-    let database = Arc::new(RwLock::new(
-        Database::new(&config.database_address, config.database_port).await?,
-    ));
+/// Directory the page templates are read from, on every request
+#[derive(Clone)]
+struct Views(Arc<std::path::Path>);
 
-    // Get the current directory
-    let current_dir = std::env::current_dir()?;
-    println!("Current directory: {:?}", current_dir);
+impl Views {
+    fn template(&self, name: &str) -> std::path::PathBuf {
+        self.0.join(name)
+    }
+}
 
-    // Build the path to the template file
-    let template_path = current_dir.join("views").join("index.html.in");
-    println!("Template path: {:?}", template_path);
+/// Page templates in the views directory, read again on every request
+const TEMPLATES: [&str; 3] = ["index.html.in", "post_detail.html.in", "search.html.in"];
+
+/// Make sure every page template can be read and parses
+fn check_templates(views: &Views) -> anyhow::Result<()> {
+    for name in TEMPLATES {
+        let path = views.template(name);
+        let source = read_file(&path)
+            .with_context(|| format!("could not read template {}", path.display()))?;
+        template_env()
+            .add_template(name, &source)
+            .with_context(|| format!("could not parse template {}", path.display()))?;
+    }
+    Ok(())
+}
 
-    // Verify the template file exists
-    if (!template_path.exists()) {
-        return Err(anyhow::anyhow!(
-            "Template file not found at: {:?}",
-            template_path
-        ));
+/// Links of the navbar: the database menu named by `navbar.menu`, or the
+/// `[navbar]` links of the config while that menu doesn't exist or has no
+/// items. A database error also falls back, so pages still render.
+async fn navbar_links(database: &DatabaseT, config: &CmsRustConfig) -> Vec<MenuLink> {
+    if !config.navbar.menu.is_empty() {
+        match database.find_menu(&config.navbar.menu).await {
+            Ok(Some(menu)) if !menu.items.is_empty() => return MenuLink::from_menu(&menu.items),
+            Ok(_) => {}
+            Err(e) => tracing::warn!(
+                "could not load the `{}` menu: {}",
+                config.navbar.menu,
+                e.err_msg
+            ),
+        }
     }
 
-    // Axum for multiplexing the http connections to endpoints
-    // build our application with a route
-    let app = Router::new()
+    MenuLink::from_config(&config.navbar)
+}
+
+/// All routes of the public site, served from `database`. Any
+/// `Repository` works, so the site can also run without a database.
+fn app_router(
+    database: DatabaseT,
+    config: SharedConfig,
+    page_cache: Arc<PageCache>,
+    views: Views,
+) -> Router {
+    // Set at startup, like the other keys the layers use
+    let security_headers = config.load().security_headers.clone();
+
+    // Routes whose rendered output goes through the page cache
+    let cached_routes = Router::<AppState>::new()
         // `GET /` goes to `root`
         .route("/", get(home_handler))
         .route("/post/:id", get(post_detail_handler)) // Add route for single post
-        .layer(Extension(database))
-        .layer(Extension(config.clone()));
+        .route("/feed.xml", get(feeds::rss_handler))
+        .route("/atom.xml", get(feeds::atom_handler))
+        .route("/sitemap.xml", get(sitemap::sitemap_handler))
+        .route("/sitemap/:file", get(sitemap::sitemap_part_handler))
+        .route_layer(middleware::from_fn_with_state(page_cache, page_cache_layer));
 
-    // run our app with hyper, listening globally on port 3000
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", config.webserver_port))
-        .await
-        .unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Axum for multiplexing the http connections to endpoints
+    // build our application with a route
+    let router = Router::new()
+        .merge(cached_routes)
+        .route("/search", get(search_handler))
+        .route("/robots.txt", get(sitemap::robots_handler))
+        .route("/livez", get(common::livez_handler))
+        .route("/readyz", get(readyz_handler))
+        .layer(middleware::from_fn(conditional_get_layer))
+        .with_state(AppState {
+            database,
+            config,
+            views,
+        });
+    let router = common::with_security_headers(router, &security_headers);
+
+    common::with_request_tracing(common::with_request_metrics(router))
+}
+
+async fn try_main(args: ProgramArgs) -> anyhow::Result<()> {
+    // Read the config
+    let config = Arc::new(CmsRustConfig::new(&args.config_file)?);
+    common::init_tracing(&config.logging)?;
+
+    tracing::info!("config:\n{}", config.summary());
+
+    let database = Database::connect(&config).await?;
+    database.check_schema(args.migrate).await?;
+    let pool = database.clone();
+    let database: DatabaseT = Arc::new(database);
+
+    // Get the current directory
+    let current_dir = std::env::current_dir()?;
+    tracing::debug!("current directory: {:?}", current_dir);
+
+    // Verify the templates are there, rather than failing on the first page
+    let views = Views(current_dir.join("views").into());
+    check_templates(&views)?;
+
+    // Rendered pages are kept in memory when `cache_enabled` is set. The
+    // poller runs either way, since a reload may turn the cache on.
+    let page_cache = Arc::new(PageCache::new(config.cache_enabled, &config.cache));
+    let shared_config: SharedConfig = Arc::new(ArcSwap::new(config.clone()));
+    cache_invalidation::spawn_poller(database.clone(), page_cache.clone(), shared_config.clone());
+    config_reload::spawn_reloader(
+        args.config_file.clone(),
+        shared_config.clone(),
+        page_cache.clone(),
+    );
+
+    if config.metrics.enabled {
+        common::spawn_metrics_server(
+            &config.metrics,
+            config.metrics.webserver_port,
+            pool.clone(),
+            Some(page_cache.clone()),
+        )
+        .await?;
+    }
+
+    let app = common::with_hsts(
+        app_router(database, shared_config, page_cache, views),
+        &config,
+    );
+
+    // run our app with hyper, listening on the configured address or socket
+    let tls = common::load_tls(&config).await?;
+    let listener = common::bind_app(
+        &config.webserver_bind,
+        config.webserver_port,
+        config.webserver_socket.as_deref(),
+        // validated when the config was loaded
+        config.socket_mode().unwrap_or(0o660),
+    )
+    .await?;
+    common::spawn_https_redirect(&config).await?;
+    common::serve(
+        listener,
+        app,
+        Duration::from_secs(config.shutdown_delay_secs),
+        Duration::from_secs(config.shutdown_timeout_secs),
+        tls,
+    )
+    .await?;
+
+    // Requests are done or dropped, so nothing uses the pool anymore
+    pool.close().await?;
+    tracing::info!("stopped");
 
     Ok(())
 }
 
 #[tokio::main]
-async fn main() {
-    match try_main().await {
+async fn main() -> ExitCode {
+    let args = ProgramArgs::parse();
+
+    if let Some(Command::CheckConfig) = args.command {
+        let report = common::check_config(&args.config_file).await;
+        println!("{}", report);
+        return match report.has_errors() {
+            true => ExitCode::FAILURE,
+            false => ExitCode::SUCCESS,
+        };
+    }
+
+    match try_main(args).await {
+        Ok(()) => ExitCode::SUCCESS,
         Err(e) => {
-            println!("exited program, error: {:?}", e);
+            common::report_fatal(&e);
+            ExitCode::FAILURE
         }
-        _ => {}
     }
 }
 
-fn default_page_num() -> i32 {
+/// `GET /readyz`: the database is reachable with a current schema, and
+/// the templates parse
+#[debug_handler(state = AppState)]
+async fn readyz_handler(
+    State(database): State<DatabaseT>,
+    State(views): State<Views>,
+) -> Result<Json<ProbeResponse>, AppError> {
+    let checks = async {
+        database.check_ready().await?;
+        check_templates(&views)
+    };
+    common::readiness_response(checks.await)
+}
+
+fn default_page_num() -> u64 {
     0
 }
 
 #[derive(Deserialize)]
 struct HomeHandlerParams {
     #[serde(default = "default_page_num")]
-    page_num: i32,
+    page_num: u64,
 }
 
-#[debug_handler]
+/// Page links rendered below the post listing
+#[derive(Serialize)]
+struct PaginationContext {
+    page: u64,
+    total_pages: u64,
+    prev_page: Option<u64>,
+    next_page: Option<u64>,
+}
+
+#[debug_handler(state = AppState)]
 async fn home_handler(
-    Extension(database_lock): Extension<DatabaseT>,
-    Extension(config): Extension<Arc<CmsRustConfig>>,
+    State(database): State<DatabaseT>,
+    State(config): State<Arc<CmsRustConfig>>,
+    State(views): State<Views>,
+    nonce: CspNonce,
     Query(home_params): Query<HomeHandlerParams>,
-) -> Result<Html<String>, Json<AppError>> {
-    let database = database_lock.read().await;
-    let posts = database.get_posts(home_params.page_num, -1).await?;
+) -> Result<Html<String>, AppError> {
+    let posts_page = database
+        .get_posts(&PostsQuery {
+            page: home_params.page_num,
+            per_page: config.home_page_size,
+            ..Default::default()
+        })
+        .await?;
 
-    let current_dir = std::env::current_dir().map_err(|e| AppError {
-        err_msg: e.to_string(),
-        status_code: StatusCode::INTERNAL_SERVER_ERROR,
-    })?;
+    let total_pages = posts_page.total.div_ceil(posts_page.per_page);
+    let canonical_path = match home_params.page_num {
+        0 => "/".to_string(),
+        page_num => format!("/?page_num={}", page_num),
+    };
+    let pagination = PaginationContext {
+        page: home_params.page_num,
+        total_pages,
+        prev_page: home_params.page_num.checked_sub(1),
+        next_page: posts_page
+            .next_cursor
+            .as_ref()
+            .map(|_| home_params.page_num + 1),
+    };
 
-    let template_path = current_dir.join("views").join("index.html.in");
+    let template_path = views.template("index.html.in");
 
     let html = read_file(template_path).map_err(|e| AppError {
         err_msg: format!("Failed to read template file: {}", e),
         status_code: StatusCode::INTERNAL_SERVER_ERROR,
     })?;
 
-    let mut env = Environment::new();
-    env.add_filter("markdown", markdown_filter);
+    let mut env = template_env();
 
     env.add_template("index", &html).map_err(|_| AppError {
         err_msg: "could not parse template".into(),
@@ -147,48 +362,50 @@ async fn home_handler(
         status_code: StatusCode::INTERNAL_SERVER_ERROR,
     })?;
 
+    let menu = navbar_links(&database, &config).await;
+    let started = Instant::now();
     let template = tmpl
         .render(context! {
-            posts => posts,
-            navbar => config.navbar
+            posts => posts_page.items,
+            pagination => pagination,
+            seo => SeoContext::for_page(&config, &config.site_title, &canonical_path, false),
+            menu => menu,
+            csp_nonce => nonce.0
         })
         .map_err(|_| AppError {
             err_msg: "could not render template".into(),
             status_code: StatusCode::INTERNAL_SERVER_ERROR,
         })?;
+    common::record_template_render("index", started.elapsed());
 
     Ok(Html(template))
 }
 
 // Add handler for single post page
-#[debug_handler]
+#[debug_handler(state = AppState)]
 async fn post_detail_handler(
-    Extension(database_lock): Extension<DatabaseT>,
-    Extension(config): Extension<Arc<CmsRustConfig>>,
+    State(database): State<DatabaseT>,
+    State(config): State<Arc<CmsRustConfig>>,
+    State(views): State<Views>,
+    nonce: CspNonce,
     Path(post_id): Path<i32>, // This should now correctly resolve to axum::extract::Path
-) -> Result<Html<String>, Json<AppError>> {
-    let database = database_lock.read().await;
+) -> Result<Html<String>, AppError> {
     let post_result = database.get_post(post_id).await;
 
     // Handle potential database error (e.g., post not found)
     let post = match post_result {
         Ok(p) => p,
-        Err(e) => {
+        Err(_) => {
             // Log the error if possible
             // eprintln!("Error fetching post {}: {}", post_id, e);
-            return Err(Json(AppError {
+            return Err(AppError {
                 err_msg: format!("Post with ID {} not found or database error.", post_id),
                 status_code: StatusCode::NOT_FOUND, // Use NOT_FOUND for missing resource
-            }));
+            });
         }
     };
 
-    let current_dir = std::env::current_dir().map_err(|e| AppError {
-        err_msg: e.to_string(),
-        status_code: StatusCode::INTERNAL_SERVER_ERROR,
-    })?;
-
-    let template_path = current_dir.join("views").join("post_detail.html.in");
+    let template_path = views.template("post_detail.html.in");
 
     // Use io::Error for file reading errors
     let html = read_file(&template_path).map_err(|e: io::Error| AppError {
@@ -200,8 +417,7 @@ async fn post_detail_handler(
         status_code: StatusCode::INTERNAL_SERVER_ERROR,
     })?;
 
-    let mut env = Environment::new();
-    env.add_filter("markdown", markdown_filter);
+    let mut env = template_env();
 
     env.add_template("post_detail", &html)
         .map_err(|e| AppError {
@@ -214,12 +430,222 @@ async fn post_detail_handler(
         status_code: StatusCode::INTERNAL_SERVER_ERROR,
     })?;
 
+    let menu = navbar_links(&database, &config).await;
+    let started = Instant::now();
     let rendered_html = tmpl
-        .render(context!(post => post, navbar => &config.navbar))
+        .render(context!(
+            seo => SeoContext::for_post(&config, &post),
+            post => post,
+            menu => menu,
+            csp_nonce => nonce.0
+        ))
         .map_err(|e| AppError {
             err_msg: format!("Failed to render template: {}", e),
             status_code: StatusCode::INTERNAL_SERVER_ERROR,
         })?;
+    common::record_template_render("post_detail", started.elapsed());
 
     Ok(Html(rendered_html))
 }
+
+#[derive(Deserialize)]
+struct SearchHandlerParams {
+    #[serde(default)]
+    q: String,
+    #[serde(default = "default_page_num")]
+    page: u64,
+}
+
+#[debug_handler(state = AppState)]
+async fn search_handler(
+    State(database): State<DatabaseT>,
+    State(config): State<Arc<CmsRustConfig>>,
+    State(views): State<Views>,
+    nonce: CspNonce,
+    Query(search_params): Query<SearchHandlerParams>,
+) -> Result<Html<String>, AppError> {
+    let results = database
+        .search_posts(&SearchQuery {
+            q: search_params.q.clone(),
+            page: search_params.page,
+            per_page: config.home_page_size,
+        })
+        .await?;
+
+    let total_pages = results.total.div_ceil(results.per_page);
+    let pagination = PaginationContext {
+        page: search_params.page,
+        total_pages,
+        prev_page: search_params.page.checked_sub(1),
+        next_page: Some(search_params.page + 1).filter(|next| *next < total_pages),
+    };
+
+    let template_path = views.template("search.html.in");
+
+    let html = read_file(&template_path).map_err(|e: io::Error| AppError {
+        err_msg: format!(
+            "Failed to read template file '{}': {}",
+            template_path.display(),
+            e
+        ),
+        status_code: StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+
+    let mut env = template_env();
+
+    env.add_template("search", &html).map_err(|e| AppError {
+        err_msg: format!("Failed to add template: {}", e),
+        status_code: StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+
+    let tmpl = env.get_template("search").map_err(|e| AppError {
+        err_msg: format!("Failed to get template: {}", e),
+        status_code: StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+
+    let menu = navbar_links(&database, &config).await;
+    let started = Instant::now();
+    let rendered_html = tmpl
+        .render(context! {
+            seo => SeoContext::for_page(&config, "Search", "/search", true),
+            query => search_params.q,
+            results => results,
+            pagination => pagination,
+            menu => menu,
+            csp_nonce => nonce.0
+        })
+        .map_err(|e| AppError {
+            err_msg: format!("Failed to render template: {}", e),
+            status_code: StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+    common::record_template_render("search", started.elapsed());
+
+    Ok(Html(rendered_html))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, HeaderMap, Request};
+    use common::{AddPostRequest, MemoryRepository, PostRepository};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// The public router over `repository`, with the sample config and
+    /// the page cache off
+    fn router(repository: MemoryRepository) -> Router {
+        router_with_views(repository, concat!(env!("CARGO_MANIFEST_DIR"), "/../views"))
+    }
+
+    /// `router`, reading the templates from `views_dir`
+    fn router_with_views(repository: MemoryRepository, views_dir: &str) -> Router {
+        let config = CmsRustConfig::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../cms_rust_config.toml"
+        ))
+        .unwrap();
+        let page_cache = Arc::new(PageCache::new(false, &config.cache));
+        let views = Views(std::path::Path::new(views_dir).into());
+        app_router(
+            Arc::new(repository),
+            Arc::new(ArcSwap::from_pointee(config)),
+            page_cache,
+            views,
+        )
+    }
+
+    async fn add_post(repository: &MemoryRepository, title: &str, content: &str) -> i32 {
+        repository
+            .add_post(&AddPostRequest {
+                title: title.into(),
+                content: content.into(),
+                excerpt: "An excerpt".into(),
+                seo: Default::default(),
+            })
+            .await
+            .unwrap()
+    }
+
+    /// Status, headers and body of the page at `uri`
+    async fn get(router: &Router, uri: &str) -> (StatusCode, HeaderMap, String) {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, headers, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn home_page_lists_the_posts() {
+        let repository = MemoryRepository::new();
+        add_post(&repository, "Hello <world>", "Text").await;
+        add_post(&repository, "Second post", "Text").await;
+        let router = router(repository);
+
+        let (status, headers, page) = get(&router, "/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "text/html; charset=utf-8");
+        assert!(headers.contains_key(header::ETAG));
+        assert!(page.contains("Hello &lt;world&gt;"));
+        assert!(page.contains("Second post"));
+        assert!(!page.contains("Hello <world>"));
+    }
+
+    #[tokio::test]
+    async fn post_page_renders_the_markdown() {
+        let repository = MemoryRepository::new();
+        let post_id = add_post(&repository, "Markdown", "Some *emphasis*").await;
+        let router = router(repository);
+
+        let (status, _, page) = get(&router, &format!("/post/{}", post_id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains("<em>emphasis</em>"));
+    }
+
+    #[tokio::test]
+    async fn missing_post_is_not_found() {
+        let router = router(MemoryRepository::new());
+
+        let (status, _, _) = get(&router, "/post/42").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn search_page_shows_the_matching_posts() {
+        let repository = MemoryRepository::new();
+        add_post(&repository, "About Rust", "Ownership and borrowing").await;
+        add_post(&repository, "About cooking", "Pasta").await;
+        let router = router(repository);
+
+        let (status, _, page) = get(&router, "/search?q=borrowing").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(page.contains("About Rust"));
+        assert!(!page.contains("About cooking"));
+    }
+
+    #[tokio::test]
+    async fn search_page_escapes_the_query() {
+        let router = router(MemoryRepository::new());
+
+        let (status, _, page) = get(&router, "/search?q=%3Cscript%3Ealert(1)%3C%2Fscript%3E").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!page.contains("<script>alert(1)</script>"));
+        assert!(page.contains("&lt;script&gt;alert(1)&lt;&#x2f;script&gt;"));
+    }
+
+    #[tokio::test]
+    async fn readiness_checks_the_templates() {
+        let router = router(MemoryRepository::new());
+        let (status, _, body) = get(&router, "/readyz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, r#"{"status":"ok"}"#);
+
+        let views_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/no-such-views");
+        let router = router_with_views(MemoryRepository::new(), views_dir);
+        let (status, _, body) = get(&router, "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(body.contains("could not read template"));
+    }
+}
@@ -0,0 +1,83 @@
+use common::{CmsRustConfig, GetPostResponse};
+use serde::Serialize;
+use serde_json::json;
+
+/// Metadata rendered into the `<head>` of the public pages: the meta
+/// description, canonical link, OpenGraph and Twitter card tags, and
+/// JSON-LD structured data
+#[derive(Serialize)]
+pub struct SeoContext {
+    pub title: String,
+    pub description: String,
+    pub canonical_url: String,
+    // OpenGraph object type, `article` for posts
+    pub og_type: &'static str,
+    pub image: Option<String>,
+    pub site_name: String,
+    pub noindex: bool,
+    // JSON-LD document, already escaped for use in a `<script>` tag
+    pub json_ld: Option<String>,
+}
+
+impl SeoContext {
+    /// Metadata for a listing page of the site itself, such as the home page
+    pub fn for_page(config: &CmsRustConfig, title: &str, path: &str, noindex: bool) -> Self {
+        SeoContext {
+            title: title.to_string(),
+            description: config.site_description.clone(),
+            canonical_url: config.absolute_url(path),
+            og_type: "website",
+            image: None,
+            site_name: config.site_title.clone(),
+            noindex,
+            json_ld: None,
+        }
+    }
+
+    pub fn for_post(config: &CmsRustConfig, post: &GetPostResponse) -> Self {
+        let description = post
+            .seo
+            .meta_description
+            .clone()
+            .unwrap_or_else(|| post.excerpt.clone());
+        let canonical_url = post
+            .seo
+            .canonical_url
+            .clone()
+            .unwrap_or_else(|| config.absolute_url(&format!("/post/{}", post.post_id)));
+        let image = post.seo.og_image.as_ref().map(|image| {
+            if image.starts_with('/') {
+                config.absolute_url(image)
+            } else {
+                image.clone()
+            }
+        });
+
+        let mut json_ld = json!({
+            "@context": "https://schema.org",
+            "@type": "BlogPosting",
+            "headline": post.title,
+            "description": description,
+            "datePublished": post.created_at.to_rfc3339(),
+            "dateModified": post.updated_at.to_rfc3339(),
+            "url": canonical_url,
+            "mainEntityOfPage": { "@type": "WebPage", "@id": canonical_url },
+            "publisher": { "@type": "Organization", "name": config.site_title },
+        });
+        if let Some(image) = &image {
+            json_ld["image"] = json!(image);
+        }
+
+        SeoContext {
+            title: post.title.clone(),
+            description,
+            canonical_url,
+            og_type: "article",
+            image,
+            site_name: config.site_title.clone(),
+            noindex: post.seo.noindex,
+            // `</` would end the script element early
+            json_ld: Some(json_ld.to_string().replace("</", "<\\/")),
+        }
+    }
+}
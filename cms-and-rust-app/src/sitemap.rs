@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use common::{AppError, CmsRustConfig, PostSortField, PostStamp, PostsQuery};
+use sea_orm::prelude::DateTimeUtc;
+
+use crate::{feeds::xml_escape, DatabaseT};
+
+/// Most URLs a single sitemap may hold under the sitemaps.org protocol.
+/// Each entry is well under 1KB, so the 50MB size cap is never the
+/// tighter limit.
+const SITEMAP_MAX_URLS: u64 = 50_000;
+
+/// Pages that are not posts, listed ahead of the posts
+const STATIC_PAGES: &[&str] = &["/"];
+
+const XML_CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+
+fn url_entry(xml: &mut String, loc: &str, lastmod: Option<DateTimeUtc>) {
+    xml.push_str("<url><loc>");
+    xml.push_str(&xml_escape(loc));
+    xml.push_str("</loc>");
+    if let Some(lastmod) = lastmod {
+        xml.push_str(&format!("<lastmod>{}</lastmod>", lastmod.to_rfc3339()));
+    }
+    xml.push_str("</url>\n");
+}
+
+/// Render one sitemap holding entries `start..start + SITEMAP_MAX_URLS`
+/// of the static pages followed by all posts
+async fn sitemap_part(
+    database: &DatabaseT,
+    config: &CmsRustConfig,
+    start: u64,
+) -> Result<String, AppError> {
+    let end = start + SITEMAP_MAX_URLS;
+    let static_count = STATIC_PAGES.len() as u64;
+
+    // The static pages change whenever the newest post does
+    let latest_update = database
+        .get_posts(&PostsQuery {
+            per_page: 1,
+            sort: PostSortField::UpdatedAt,
+            ..Default::default()
+        })
+        .await?
+        .items
+        .first()
+        .map(|post| post.updated_at);
+
+    let post_start = start.saturating_sub(static_count);
+    let post_end = end.saturating_sub(static_count);
+    let posts: Vec<PostStamp> = database
+        .get_post_stamps(post_start, post_end - post_start)
+        .await?;
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for page in STATIC_PAGES
+        .iter()
+        .skip(start as usize)
+        .take((end - start) as usize)
+    {
+        url_entry(&mut xml, &config.absolute_url(page), latest_update);
+    }
+    for post in posts {
+        url_entry(
+            &mut xml,
+            &config.absolute_url(&format!("/post/{}", post.post_id)),
+            Some(post.updated_at),
+        );
+    }
+    xml.push_str("</urlset>\n");
+
+    Ok(xml)
+}
+
+/// Either the only sitemap, or a sitemap index pointing at
+/// `/sitemap/{n}.xml` parts when there are too many URLs for one
+#[debug_handler(state = crate::AppState)]
+pub async fn sitemap_handler(
+    State(database): State<DatabaseT>,
+    State(config): State<Arc<CmsRustConfig>>,
+) -> Result<impl IntoResponse, AppError> {
+    let total = database.count_indexable_posts().await? + STATIC_PAGES.len() as u64;
+
+    if total <= SITEMAP_MAX_URLS {
+        let xml = sitemap_part(&database, &config, 0).await?;
+        return Ok(([(header::CONTENT_TYPE, XML_CONTENT_TYPE)], xml));
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for part in 0..total.div_ceil(SITEMAP_MAX_URLS) {
+        xml.push_str("<sitemap><loc>");
+        xml.push_str(&xml_escape(
+            &config.absolute_url(&format!("/sitemap/{}.xml", part + 1)),
+        ));
+        xml.push_str("</loc></sitemap>\n");
+    }
+    xml.push_str("</sitemapindex>\n");
+
+    Ok(([(header::CONTENT_TYPE, XML_CONTENT_TYPE)], xml))
+}
+
+/// One part of a split sitemap, numbered from 1
+#[debug_handler(state = crate::AppState)]
+pub async fn sitemap_part_handler(
+    State(database): State<DatabaseT>,
+    State(config): State<Arc<CmsRustConfig>>,
+    Path(file_name): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let not_found = || AppError {
+        err_msg: format!("sitemap {} not found", file_name),
+        status_code: StatusCode::NOT_FOUND,
+    };
+
+    let part = file_name
+        .strip_suffix(".xml")
+        .and_then(|number| number.parse::<u64>().ok())
+        .filter(|part| *part >= 1)
+        .ok_or_else(not_found)?;
+
+    let total = database.count_indexable_posts().await? + STATIC_PAGES.len() as u64;
+    if part > total.div_ceil(SITEMAP_MAX_URLS) {
+        return Err(not_found());
+    }
+
+    let xml = sitemap_part(&database, &config, (part - 1) * SITEMAP_MAX_URLS).await?;
+
+    Ok(([(header::CONTENT_TYPE, XML_CONTENT_TYPE)], xml))
+}
+
+#[debug_handler(state = crate::AppState)]
+pub async fn robots_handler(State(config): State<Arc<CmsRustConfig>>) -> impl IntoResponse {
+    let mut robots = String::from("User-agent: *\n");
+    for path in &config.robots.allow {
+        robots.push_str(&format!("Allow: {}\n", path));
+    }
+    for path in &config.robots.disallow {
+        robots.push_str(&format!("Disallow: {}\n", path));
+    }
+    if config.robots.allow.is_empty() && config.robots.disallow.is_empty() {
+        // An empty Disallow allows everything
+        robots.push_str("Disallow:\n");
+    }
+    robots.push_str(&format!(
+        "\nSitemap: {}\n",
+        config.absolute_url("/sitemap.xml")
+    ));
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        robots,
+    )
+}
@@ -41,9 +41,45 @@ impl IntoResponse for AppError {
         let json_data = serde_json::to_string(&self).unwrap();
 
         Response::builder()
-            .status(StatusCode::OK)
+            .status(self.status_code)
             .header("Content-Type", "application/json")
             .body(Body::from(json_data))
             .unwrap()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn responses_carry_the_status_code() {
+        for status_code in [
+            StatusCode::BAD_REQUEST,
+            StatusCode::NOT_FOUND,
+            StatusCode::CONFLICT,
+            StatusCode::PRECONDITION_FAILED,
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ] {
+            let response = AppError {
+                err_msg: "went wrong".into(),
+                status_code,
+            }
+            .into_response();
+
+            assert_eq!(response.status(), status_code);
+            assert_eq!(response.headers()["content-type"], "application/json");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(
+                json,
+                serde_json::json!({
+                    "err_msg": "went wrong",
+                    "status_code": status_code.as_u16(),
+                })
+            );
+        }
+    }
+}
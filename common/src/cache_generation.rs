@@ -0,0 +1,211 @@
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ActiveModelBehavior, ColumnTrait, ConnectionTrait, DbErr, DeriveEntityModel, DerivePrimaryKey,
+    DeriveRelation, EntityTrait, EnumIter, PrimaryKeyTrait, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+
+/// Generation counter of a group of cached pages. The admin app bumps
+/// it on every write, and the public app polls for newer generations
+/// to know which of its cached pages are stale.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "cache_generation")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub cache_key: String,
+    pub generation: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// The single row counting the generations handed out so far
+mod counter {
+    use sea_orm::{
+        ActiveModelBehavior, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EnumIter,
+        PrimaryKeyTrait,
+    };
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "cache_generation_counter")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: i32,
+        pub generation: i64,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+
+    /// Id of the counter's row
+    pub const ID: i32 = 1;
+}
+
+/// Group of cached pages that a write can make stale
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CacheScope {
+    // the page of a single post
+    Post(i32),
+    // pages listing posts, such as the home page and the sitemap
+    Listing,
+    // the RSS and Atom feeds
+    Feed,
+    // every page, as they all show the navbar menu
+    Menu,
+}
+
+impl CacheScope {
+    /// Scopes made stale by creating, editing or deleting a post
+    pub(crate) fn for_post_write(post_id: i32) -> [CacheScope; 3] {
+        [
+            CacheScope::Post(post_id),
+            CacheScope::Listing,
+            CacheScope::Feed,
+        ]
+    }
+
+    /// Scopes made stale by deleting a post, which also turns menu
+    /// items linking to it into headings
+    pub(crate) fn for_post_delete(post_id: i32) -> [CacheScope; 4] {
+        [
+            CacheScope::Post(post_id),
+            CacheScope::Listing,
+            CacheScope::Feed,
+            CacheScope::Menu,
+        ]
+    }
+
+    fn key(&self) -> String {
+        match self {
+            CacheScope::Post(post_id) => format!("post:{}", post_id),
+            CacheScope::Listing => "listing".into(),
+            CacheScope::Feed => "feed".into(),
+            CacheScope::Menu => "menu".into(),
+        }
+    }
+
+    fn parse(key: &str) -> Option<Self> {
+        match key {
+            "listing" => Some(CacheScope::Listing),
+            "feed" => Some(CacheScope::Feed),
+            "menu" => Some(CacheScope::Menu),
+            _ => key
+                .strip_prefix("post:")
+                .and_then(|post_id| post_id.parse().ok())
+                .map(CacheScope::Post),
+        }
+    }
+}
+
+/// Highest generation bumped so far, 0 when nothing was ever written
+pub(crate) async fn latest<C: ConnectionTrait>(db: &C) -> Result<i64, DbErr> {
+    let counter = counter::Entity::find_by_id(counter::ID)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("cache_generation_counter".into()))?;
+
+    Ok(counter.generation)
+}
+
+/// Move the given scopes to a new generation, newer than any before it.
+/// `db` must be the transaction of the write, as the counter row stays
+/// locked until it ends: a concurrent write waits for the commit and then
+/// gets the next generation, so generations are unique and the public app
+/// sees them committed in order.
+pub(crate) async fn bump<C: ConnectionTrait>(db: &C, scopes: &[CacheScope]) -> Result<i64, DbErr> {
+    counter::Entity::update_many()
+        .col_expr(
+            counter::Column::Generation,
+            Expr::col(counter::Column::Generation).add(1),
+        )
+        .filter(counter::Column::Id.eq(counter::ID))
+        .exec(db)
+        .await?;
+    let generation = latest(db).await?;
+
+    let rows = scopes.iter().map(|scope| ActiveModel {
+        cache_key: sea_orm::ActiveValue::Set(scope.key()),
+        generation: sea_orm::ActiveValue::Set(generation),
+    });
+
+    Entity::insert_many(rows)
+        .on_conflict(
+            OnConflict::column(Column::CacheKey)
+                .update_column(Column::Generation)
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+
+    Ok(generation)
+}
+
+/// Scopes bumped after `generation`, with their new generation
+pub(crate) async fn since<C: ConnectionTrait>(
+    db: &C,
+    generation: i64,
+) -> Result<Vec<(CacheScope, i64)>, DbErr> {
+    let rows = Entity::find()
+        .filter(Column::Generation.gt(generation))
+        .all(db)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| CacheScope::parse(&row.cache_key).map(|scope| (scope, row.generation)))
+        .collect())
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use sea_orm::TransactionTrait;
+
+    use super::*;
+    use crate::test_database::connect;
+
+    #[tokio::test]
+    async fn concurrent_bumps_get_unique_generations() {
+        let db = connect("concurrent-bumps").await;
+
+        let writes = (0..24).map(|post_id| {
+            let db = db.clone();
+            tokio::spawn(async move {
+                let txn = db.begin().await.unwrap();
+                let generation = bump(&txn, &CacheScope::for_post_write(post_id))
+                    .await
+                    .unwrap();
+                // let the other writes run while this one is uncommitted
+                tokio::task::yield_now().await;
+                txn.commit().await.unwrap();
+                generation
+            })
+        });
+        let mut generations = Vec::new();
+        for write in writes.collect::<Vec<_>>() {
+            generations.push(write.await.unwrap());
+        }
+        generations.sort();
+
+        assert_eq!(generations, (1..=24).collect::<Vec<i64>>());
+        assert_eq!(latest(&db).await.unwrap(), 24);
+        let stale = since(&db, 0).await.unwrap();
+        assert!(stale.contains(&(CacheScope::Listing, 24)));
+        assert_eq!(stale.len(), 24 + 2);
+    }
+
+    #[tokio::test]
+    async fn rolled_back_bumps_are_not_seen() {
+        let db = connect("rolled-back-bump").await;
+
+        let txn = db.begin().await.unwrap();
+        bump(&txn, &[CacheScope::Feed]).await.unwrap();
+        txn.rollback().await.unwrap();
+
+        assert_eq!(latest(&db).await.unwrap(), 0);
+        assert!(since(&db, 0).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+use std::time::SystemTime;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use sea_orm::prelude::DateTimeUtc;
+use sha2::{Digest, Sha256};
+
+use crate::{security_headers::replace_nonce, CspNonce};
+
+/// Headers a `304 Not Modified` carries over from the full response
+const NOT_MODIFIED_HEADERS: [header::HeaderName; 4] = [
+    header::ETAG,
+    header::LAST_MODIFIED,
+    header::CACHE_CONTROL,
+    header::VARY,
+];
+
+/// Strong ETag for a representation, derived from its bytes
+pub fn etag_for(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    format!("\"{}\"", hex::encode(&digest[..16]))
+}
+
+/// `Last-Modified` header value for a timestamp
+pub fn last_modified(timestamp: DateTimeUtc) -> HeaderValue {
+    HeaderValue::from_str(&httpdate::fmt_http_date(SystemTime::from(timestamp))).unwrap()
+}
+
+/// Entity tags listed in the given `If-None-Match` or `If-Match` headers
+fn listed_etags(headers: &HeaderMap, name: header::HeaderName) -> Vec<String> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Whether an `If-Match` precondition holds for the current ETag of a
+/// resource. Requests without `If-Match` always pass. Weak tags never
+/// match, as `If-Match` uses the strong comparison.
+pub fn if_match_holds(headers: &HeaderMap, current_etag: &str) -> bool {
+    let tags = listed_etags(headers, header::IF_MATCH);
+    tags.is_empty() || tags.iter().any(|tag| tag == "*" || tag == current_etag)
+}
+
+/// Whether the client's cached copy is still current, following the
+/// precedence of RFC 9110: `If-Modified-Since` only counts when the
+/// request has no `If-None-Match`
+fn is_not_modified(request_headers: &HeaderMap, response_headers: &HeaderMap) -> bool {
+    let if_none_match = listed_etags(request_headers, header::IF_NONE_MATCH);
+    if !if_none_match.is_empty() {
+        let Some(etag) = response_headers
+            .get(header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+        else {
+            return false;
+        };
+
+        // `If-None-Match` uses the weak comparison
+        let etag = etag.trim_start_matches("W/");
+        return if_none_match
+            .iter()
+            .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag);
+    }
+
+    let parse_date = |headers: &HeaderMap, name| {
+        headers
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+            .and_then(|value| httpdate::parse_http_date(value).ok())
+    };
+
+    match (
+        parse_date(request_headers, header::IF_MODIFIED_SINCE),
+        parse_date(response_headers, header::LAST_MODIFIED),
+    ) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+/// Middleware for conditional `GET` requests. Successful responses get a
+/// strong ETag hashed from their body unless the handler set one, and
+/// `If-None-Match`/`If-Modified-Since` are answered with
+/// `304 Not Modified` when the client's copy is current.
+pub async fn conditional_get_layer(request: Request, next: Next) -> Response {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return next.run(request).await;
+    }
+
+    let request_headers = request.headers().clone();
+    // Pages name the CSP nonce of their response, which must not make
+    // each response look like a new version of the page
+    let nonce = request
+        .extensions()
+        .get::<CspNonce>()
+        .cloned()
+        .unwrap_or_default();
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = if parts.headers.contains_key(header::ETAG) {
+        body
+    } else {
+        let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+            Ok(bytes) => bytes,
+            Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        };
+        let etag = etag_for(&replace_nonce(&bytes, &nonce.0, ""));
+        parts.headers.insert(header::ETAG, etag.parse().unwrap());
+        Body::from(bytes)
+    };
+
+    if !is_not_modified(&request_headers, &parts.headers) {
+        return Response::from_parts(parts, body);
+    }
+
+    let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+    for name in NOT_MODIFIED_HEADERS {
+        if let Some(value) = parts.headers.get(&name) {
+            not_modified.headers_mut().insert(name, value.clone());
+        }
+    }
+    not_modified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ETAG: &str = "\"abc\"";
+    const MODIFIED: &str = "Tue, 15 Oct 2024 10:00:00 GMT";
+
+    fn headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn response_headers() -> HeaderMap {
+        headers(&[(header::ETAG, ETAG), (header::LAST_MODIFIED, MODIFIED)])
+    }
+
+    #[test]
+    fn if_match_without_header_holds() {
+        assert!(if_match_holds(&HeaderMap::new(), ETAG));
+    }
+
+    #[test]
+    fn if_match_star_holds() {
+        assert!(if_match_holds(&headers(&[(header::IF_MATCH, "*")]), ETAG));
+    }
+
+    #[test]
+    fn if_match_checks_every_listed_tag() {
+        let list = headers(&[(header::IF_MATCH, "\"old\", \"abc\"")]);
+        assert!(if_match_holds(&list, ETAG));
+
+        let lines = headers(&[(header::IF_MATCH, "\"old\""), (header::IF_MATCH, "\"abc\"")]);
+        assert!(if_match_holds(&lines, ETAG));
+
+        let stale = headers(&[(header::IF_MATCH, "\"old\", \"older\"")]);
+        assert!(!if_match_holds(&stale, ETAG));
+    }
+
+    #[test]
+    fn if_match_rejects_weak_tags() {
+        let weak = headers(&[(header::IF_MATCH, "W/\"abc\"")]);
+        assert!(!if_match_holds(&weak, ETAG));
+    }
+
+    #[test]
+    fn if_none_match_uses_weak_comparison() {
+        let weak = headers(&[(header::IF_NONE_MATCH, "W/\"abc\"")]);
+        assert!(is_not_modified(&weak, &response_headers()));
+
+        let weak_response = headers(&[(header::ETAG, "W/\"abc\"")]);
+        let strong = headers(&[(header::IF_NONE_MATCH, ETAG)]);
+        assert!(is_not_modified(&strong, &weak_response));
+    }
+
+    #[test]
+    fn if_none_match_star_and_lists() {
+        let star = headers(&[(header::IF_NONE_MATCH, "*")]);
+        assert!(is_not_modified(&star, &response_headers()));
+
+        let list = headers(&[(header::IF_NONE_MATCH, "\"old\", W/\"abc\"")]);
+        assert!(is_not_modified(&list, &response_headers()));
+
+        let stale = headers(&[(header::IF_NONE_MATCH, "\"old\", \"older\"")]);
+        assert!(!is_not_modified(&stale, &response_headers()));
+    }
+
+    #[test]
+    fn if_none_match_needs_an_etag() {
+        let star = headers(&[(header::IF_NONE_MATCH, "*")]);
+        let no_etag = headers(&[(header::LAST_MODIFIED, MODIFIED)]);
+        assert!(!is_not_modified(&star, &no_etag));
+    }
+
+    #[test]
+    fn if_modified_since_compares_dates() {
+        let same = headers(&[(header::IF_MODIFIED_SINCE, MODIFIED)]);
+        assert!(is_not_modified(&same, &response_headers()));
+
+        let later = headers(&[(header::IF_MODIFIED_SINCE, "Wed, 16 Oct 2024 10:00:00 GMT")]);
+        assert!(is_not_modified(&later, &response_headers()));
+
+        let earlier = headers(&[(header::IF_MODIFIED_SINCE, "Mon, 14 Oct 2024 10:00:00 GMT")]);
+        assert!(!is_not_modified(&earlier, &response_headers()));
+
+        let garbage = headers(&[(header::IF_MODIFIED_SINCE, "yesterday")]);
+        assert!(!is_not_modified(&garbage, &response_headers()));
+    }
+
+    #[test]
+    fn if_none_match_takes_precedence_over_if_modified_since() {
+        // a current date does not help a stale tag
+        let stale_tag = headers(&[
+            (header::IF_NONE_MATCH, "\"old\""),
+            (header::IF_MODIFIED_SINCE, MODIFIED),
+        ]);
+        assert!(!is_not_modified(&stale_tag, &response_headers()));
+
+        // and an old date does not count against a current tag
+        let current_tag = headers(&[
+            (header::IF_NONE_MATCH, ETAG),
+            (header::IF_MODIFIED_SINCE, "Mon, 14 Oct 2024 10:00:00 GMT"),
+        ]);
+        assert!(is_not_modified(&current_tag, &response_headers()));
+    }
+}
@@ -0,0 +1,131 @@
+use std::{fmt, fs, path::Path};
+
+use crate::{tls::read_certificate, CmsRustConfig, Database, StorageStatus};
+
+/// Outcome of `check_config`, one line per check
+#[derive(Default)]
+pub struct ConfigReport {
+    lines: Vec<(bool, String)>,
+}
+
+impl ConfigReport {
+    fn ok(&mut self, message: impl Into<String>) {
+        self.lines.push((true, message.into()));
+    }
+
+    fn error(&mut self, message: impl Into<String>) {
+        self.lines.push((false, message.into()));
+    }
+
+    /// Whether any check failed
+    pub fn has_errors(&self) -> bool {
+        self.lines.iter().any(|(ok, _)| !ok)
+    }
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (ok, message) in &self.lines {
+            writeln!(f, "{:<6} {}", if *ok { "ok" } else { "error" }, message)?;
+        }
+
+        let errors = self.lines.iter().filter(|(ok, _)| !ok).count();
+        match errors {
+            0 => write!(f, "config is valid"),
+            1 => write!(f, "1 error"),
+            _ => write!(f, "{} errors", errors),
+        }
+    }
+}
+
+/// `error` with its root cause, leaving out the layers in between, which
+/// the database errors repeat the cause in
+fn describe(error: &anyhow::Error) -> String {
+    if error.chain().count() > 1 {
+        format!("{}: {}", error, error.root_cause())
+    } else {
+        error.to_string()
+    }
+}
+
+/// Make sure `image_dir` is a directory the apps can write uploads to,
+/// by creating and removing a file in it
+fn check_image_dir(image_dir: &str) -> Result<(), String> {
+    let metadata = fs::metadata(image_dir).map_err(|e| format!("cannot be read: {}", e))?;
+    if !metadata.is_dir() {
+        return Err("is not a directory".to_string());
+    }
+
+    let probe = Path::new(image_dir).join(format!(".check-config-{}", std::process::id()));
+    fs::write(&probe, b"").map_err(|e| format!("is not writable: {}", e))?;
+    // The write worked, failing to clean up doesn't change the answer
+    let _ = fs::remove_file(&probe);
+
+    Ok(())
+}
+
+/// Load the config at `config_path` like the apps do, and check everything
+/// they need from it up front: the values themselves, the image directory,
+/// and a database that is reachable and fully migrated. Checks go on after
+/// a failure, so the report lists every problem at once.
+pub async fn check_config(config_path: &str) -> ConfigReport {
+    let mut report = ConfigReport::default();
+
+    let config = match CmsRustConfig::from_layers(config_path, std::env::vars()) {
+        Ok(config) => config,
+        Err(e) => {
+            report.error(describe(&e));
+            return report;
+        }
+    };
+    report.ok(format!("loaded {}", config_path));
+
+    let problems = config.problems();
+    if problems.is_empty() {
+        report.ok("all keys have valid values");
+    }
+    for problem in problems {
+        report.error(problem);
+    }
+
+    match check_image_dir(&config.image_dir) {
+        Ok(()) => report.ok(format!("image_dir `{}` is writable", config.image_dir)),
+        Err(reason) => report.error(format!(
+            "invalid config key `image_dir`: `{}` {}",
+            config.image_dir, reason
+        )),
+    }
+
+    if let (Some(cert), Some(key)) = (&config.tls_cert_path, &config.tls_key_path) {
+        match read_certificate(cert, key).await {
+            Ok(_) => report.ok(format!("TLS certificate `{}` and its key load", cert)),
+            Err(e) => report.error(describe(&e)),
+        }
+    }
+
+    let database = match Database::connect(&config).await {
+        Ok(database) => database,
+        Err(e) => {
+            report.error(format!("database is not reachable: {}", describe(&e)));
+            return report;
+        }
+    };
+    report.ok("database is reachable");
+
+    match database.pending_migrations().await {
+        Ok(pending) if pending.is_empty() => {
+            let version = database.schema_version().await.ok().flatten();
+            report.ok(format!(
+                "database schema is current ({})",
+                version.as_deref().unwrap_or("no migrations")
+            ));
+        }
+        Ok(pending) => report.error(format!(
+            "database schema is out of date, missing migrations: {}",
+            pending.join(", ")
+        )),
+        Err(e) => report.error(describe(&e)),
+    }
+
+    report
+}
@@ -1,18 +1,71 @@
-use std::{net::Ipv4Addr, time::Duration};
+use std::time::Duration;
 
 use anyhow::Context;
 use http::StatusCode;
+use migration::{Migrator, MigratorTrait};
 use minijinja::value::Value;
+use posts::PostCursor;
 use pulldown_cmark::{html, Options, Parser};
-use sea_orm::{ActiveModelTrait, ConnectOptions, EntityTrait, ModelTrait};
+use sea_orm::{
+    prelude::DateTimeUtc, sea_query::Expr, ActiveModelTrait, ColumnTrait, ConnectOptions,
+    ConnectionTrait, DatabaseConnection, DbBackend, DbErr, EntityTrait, ModelTrait, Order,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
 
 mod app_error;
+mod cache_generation;
+mod conditional;
 mod config;
+mod config_check;
+#[cfg(feature = "memory")]
+mod memory;
+mod menu_items;
+mod menus;
+mod page_cache;
 mod posts;
+mod prometheus;
+mod repository;
+mod search;
+mod secret;
+mod security_headers;
+mod server;
+mod telemetry;
+#[cfg(all(test, feature = "sqlite"))]
+mod test_database;
+mod tls;
 
 pub use app_error::AppError;
-pub use config::CmsRustConfig;
-pub use posts::{AddPostRequest, AddPostResponse, DeletePostResponse, GetPostResponse};
+pub use cache_generation::CacheScope;
+pub use conditional::{conditional_get_layer, etag_for, if_match_holds, last_modified};
+pub use config::{
+    CacheConfig, CmsRustConfig, DatabasePoolConfig, LogFormat, LoggingConfig, MetricsConfig,
+    SecurityHeadersConfig,
+};
+pub use config_check::{check_config, ConfigReport};
+#[cfg(feature = "memory")]
+pub use memory::MemoryRepository;
+pub use menus::{
+    AddMenuItemResponse, AddMenuRequest, AddMenuResponse, DeleteMenuItemResponse,
+    DeleteMenuResponse, MenuItemRequest, MenuItemResponse, MenuLink, MenuResponse, MenuSummary,
+};
+pub use page_cache::{page_cache_layer, CacheStats, CachedPage, PageCache};
+pub use posts::{
+    AddPostRequest, AddPostResponse, DeletePostResponse, GetPostResponse, PostCounts, PostSeo,
+    PostSortField, PostStamp, PostsPage, PostsQuery, SortOrder, UpdatePostRequest, UpdatePostSeo,
+};
+pub use prometheus::{record_template_render, spawn_metrics_server, with_request_metrics};
+pub use repository::{CacheGenerations, MenuRepository, PostRepository, Repository, StorageStatus};
+pub use search::{SearchHit, SearchQuery, SearchResults};
+pub use secret::Secret;
+pub use security_headers::{with_security_headers, CspNonce};
+pub use server::{
+    bind_app, bind_listener, livez_handler, readiness_response, serve, AppListener, ProbeResponse,
+};
+pub use telemetry::{init_tracing, report_fatal, with_request_tracing};
+pub use tls::{load_tls, spawn_https_redirect, with_hsts};
+
+/// Largest page size that `Database::get_posts` will serve
+pub const MAX_POSTS_PER_PAGE: u64 = 100;
 
 /// Convert markdown to HTML using pulldown-cmark
 pub fn markdown_to_html(text: &str) -> String {
@@ -34,72 +87,343 @@ pub fn markdown_filter(value: Value) -> Value {
     Value::from_safe_string(markdown_to_html(text))
 }
 
+#[cfg(not(any(feature = "mysql", feature = "postgres", feature = "sqlite")))]
+compile_error!("enable at least one of the `mysql`, `postgres` or `sqlite` features");
+
+/// Make sure support for the backend named by the scheme of a database
+/// URL was compiled in, so a wrong build fails with a clear message
+fn ensure_backend_enabled(url: &str) -> anyhow::Result<()> {
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .unwrap_or_default();
+    let (feature, enabled) = match scheme {
+        "mysql" => ("mysql", cfg!(feature = "mysql")),
+        "postgres" | "postgresql" => ("postgres", cfg!(feature = "postgres")),
+        "sqlite" => ("sqlite", cfg!(feature = "sqlite")),
+        _ => anyhow::bail!(
+            "unsupported database URL scheme `{}`, expected mysql, postgres or sqlite",
+            scheme
+        ),
+    };
+
+    if !enabled {
+        anyhow::bail!(
+            "{} databases need a build with the `{}` feature",
+            feature,
+            feature
+        );
+    }
+    Ok(())
+}
+
+/// Refuse page sizes outside of `1..=MAX_POSTS_PER_PAGE`
+pub(crate) fn check_per_page(per_page: u64) -> Result<(), AppError> {
+    if per_page == 0 || per_page > MAX_POSTS_PER_PAGE {
+        return Err(AppError {
+            err_msg: format!("per_page must be between 1 and {}", MAX_POSTS_PER_PAGE),
+            status_code: StatusCode::BAD_REQUEST,
+        });
+    }
+    Ok(())
+}
+
 // TODO : Move all of the database code elsewhere
 
-pub struct Database {
-    /// The IP for the database connection
-    pub ip: Ipv4Addr,
-    /// Port for the database connection
-    pub port: u16,
+/// Connections of the database pool
+#[derive(Clone, Copy, Debug)]
+pub struct PoolStatus {
+    // connections open, in use or idle
+    pub size: u32,
+    pub idle: u32,
+    // most connections the pool opens
+    pub max: u32,
+}
 
+/// Handle on the database. The connection is a pool, so clones are
+/// cheap and share it.
+#[derive(Clone)]
+pub struct Database {
     /// The underlying db connection
     _db_connection: sea_orm::DatabaseConnection,
 }
 
 impl Database {
-    pub async fn new(ip: &str, port: u16) -> anyhow::Result<Self> {
-        let config_path = std::env::current_dir()?.join("cms_rust_config.toml");
-        let config = CmsRustConfig::new(config_path.to_str().unwrap())?;
-
-        // build the connection string using config values
-        let conn_str = format!(
-            "mysql://{}:{}@{}:{}/{}",
-            config.database_user, config.database_password, ip, port, config.database_name
-        );
+    /// Connect to the database described by the loaded config. The
+    /// backend is picked from the scheme of its URL.
+    pub async fn connect(config: &CmsRustConfig) -> anyhow::Result<Self> {
+        let pool = &config.database_pool;
+        let url = config.database_url()?;
+        ensure_backend_enabled(url.expose())?;
 
         // Configure database connection options
-        let mut opt = ConnectOptions::new(conn_str);
-        opt.max_connections(100)
-            .min_connections(5)
-            .connect_timeout(Duration::from_secs(8))
-            .acquire_timeout(Duration::from_secs(8))
-            .idle_timeout(Duration::from_secs(8))
-            .max_lifetime(Duration::from_secs(8))
+        let mut opt = ConnectOptions::new(url.expose().clone());
+        opt.max_connections(pool.max_connections)
+            .min_connections(pool.min_connections)
+            .connect_timeout(Duration::from_secs(pool.connect_timeout_secs))
+            .acquire_timeout(Duration::from_secs(pool.acquire_timeout_secs))
+            .idle_timeout(Duration::from_secs(pool.idle_timeout_secs))
+            .max_lifetime(Duration::from_secs(pool.max_lifetime_secs))
             .sqlx_logging(true)
-            .sqlx_logging_level(log::LevelFilter::Info);
+            .sqlx_logging_level(log::LevelFilter::Debug);
+
+        let db = sea_orm::Database::connect(opt)
+            .await
+            .context("could not connect to the database")?;
+
+        Ok(Database { _db_connection: db })
+    }
+
+    /// Make sure the schema has every migration built into this binary.
+    /// Missing ones are applied when `migrate` is set, otherwise they are
+    /// reported as an error so the apps don't fail later at query time.
+    pub async fn check_schema(&self, migrate: bool) -> anyhow::Result<()> {
+        let names = self.pending_migrations().await?;
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        if !migrate {
+            anyhow::bail!(
+                "the database schema is out of date, missing migrations: {}. \
+                 Start with --migrate to apply them",
+                names.join(", ")
+            );
+        }
+
+        tracing::info!("applying migrations: {}", names.join(", "));
+        Migrator::up(&self._db_connection, None)
+            .await
+            .context("could not apply the migrations")?;
+
+        Ok(())
+    }
+
+    /// Close every connection of the pool, which all clones share. Queries
+    /// made afterwards fail.
+    pub async fn close(&self) -> anyhow::Result<()> {
+        self._db_connection
+            .close_by_ref()
+            .await
+            .context("could not close the database connections")
+    }
+
+    /// Connections of the pool right now, `None` once it is closed
+    pub fn pool_status(&self) -> Option<PoolStatus> {
+        macro_rules! status {
+            ($pool:expr) => {{
+                let pool = $pool;
+                PoolStatus {
+                    size: pool.size(),
+                    idle: pool.num_idle() as u32,
+                    max: pool.options().get_max_connections(),
+                }
+            }};
+        }
+
+        let db = &self._db_connection;
+        match db {
+            #[cfg(feature = "mysql")]
+            DatabaseConnection::SqlxMySqlPoolConnection(_) => {
+                Some(status!(db.get_mysql_connection_pool()))
+            }
+            #[cfg(feature = "postgres")]
+            DatabaseConnection::SqlxPostgresPoolConnection(_) => {
+                Some(status!(db.get_postgres_connection_pool()))
+            }
+            #[cfg(feature = "sqlite")]
+            DatabaseConnection::SqlxSqlitePoolConnection(_) => {
+                Some(status!(db.get_sqlite_connection_pool()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Names of the migrations not applied to the database yet
+    pub async fn pending_migrations(&self) -> anyhow::Result<Vec<String>> {
+        let pending = Migrator::get_pending_migrations(&self._db_connection)
+            .await
+            .context("could not read the migration status of the database")?;
+
+        Ok(pending
+            .iter()
+            .map(|migration| migration.name().to_string())
+            .collect())
+    }
+}
+
+fn db_error(e: DbErr) -> AppError {
+    AppError {
+        err_msg: e.to_string(),
+        status_code: StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
 
-        let db = sea_orm::Database::connect(opt).await?;
+/// `menu` with all of its items, nested
+async fn menu_response<C: ConnectionTrait>(
+    db: &C,
+    menu: menus::Model,
+) -> Result<MenuResponse, AppError> {
+    let items = menu_items::Entity::find()
+        .filter(menu_items::Column::MenuId.eq(menu.id))
+        .all(db)
+        .await
+        .map_err(db_error)?;
+
+    Ok(MenuResponse {
+        menu_id: menu.id,
+        name: menu.name,
+        items: menus::build_tree(items),
+    })
+}
+
+/// All items of a menu that is about to change. The menu row is locked,
+/// so concurrent moves can't combine into a cycle.
+async fn menu_items_for_write<C: ConnectionTrait>(
+    db: &C,
+    menu_id: i32,
+) -> Result<Vec<menu_items::Model>, AppError> {
+    menus::Entity::find_by_id(menu_id)
+        .lock_exclusive()
+        .one(db)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(menus::menu_not_found)?;
+
+    menu_items::Entity::find()
+        .filter(menu_items::Column::MenuId.eq(menu_id))
+        .all(db)
+        .await
+        .map_err(db_error)
+}
 
-        // Parse the string to IP
-        let ip = ip.parse::<Ipv4Addr>()?;
+async fn check_post_exists<C: ConnectionTrait>(
+    db: &C,
+    post_id: Option<i32>,
+) -> Result<(), AppError> {
+    let Some(post_id) = post_id else {
+        return Ok(());
+    };
 
-        Ok(Database {
-            ip,
-            port,
-            _db_connection: db,
+    posts::Entity::find_by_id(post_id)
+        .one(db)
+        .await
+        .map_err(db_error)?
+        .map(|_| ())
+        .ok_or_else(|| AppError {
+            err_msg: "could not find post id in database".into(),
+            status_code: StatusCode::BAD_REQUEST,
         })
+}
+
+/// The columns of a menu item that a request sets, leaving the ids
+fn menu_item_fields(item_request: &MenuItemRequest) -> menu_items::ActiveModel {
+    let item = item_request.to_model(0, 0);
+    menu_items::ActiveModel {
+        id: sea_orm::ActiveValue::NotSet,
+        menu_id: sea_orm::ActiveValue::NotSet,
+        parent_id: sea_orm::ActiveValue::Set(item.parent_id),
+        position: sea_orm::ActiveValue::Set(item.position),
+        label: sea_orm::ActiveValue::Set(item.label),
+        title: sea_orm::ActiveValue::Set(item.title),
+        post_id: sea_orm::ActiveValue::Set(item.post_id),
+        url: sea_orm::ActiveValue::Set(item.url),
     }
+}
+
+#[async_trait::async_trait]
+impl PostRepository for Database {
+    #[tracing::instrument(name = "db.add_post", skip(self, post_request))]
+    async fn add_post(&self, post_request: &AddPostRequest) -> anyhow::Result<i32> {
+        let seo = post_request.seo.normalized();
 
-    pub async fn add_post(&self, title: &str, excerpt: &str, content: &str) -> anyhow::Result<i32> {
         // insert everything into db with ORM
         let post = posts::ActiveModel {
             id: sea_orm::ActiveValue::NotSet,
-            title: sea_orm::ActiveValue::Set(title.to_string()),
-            content: sea_orm::ActiveValue::Set(content.to_string()),
-            excerpt: sea_orm::ActiveValue::Set(excerpt.to_string()),
+            title: sea_orm::ActiveValue::Set(post_request.title.clone()),
+            content: sea_orm::ActiveValue::Set(post_request.content.clone()),
+            excerpt: sea_orm::ActiveValue::Set(post_request.excerpt.clone()),
+            // timestamps are filled in by `ActiveModelBehavior::before_save`
+            created_at: sea_orm::ActiveValue::NotSet,
+            updated_at: sea_orm::ActiveValue::NotSet,
+            meta_description: sea_orm::ActiveValue::Set(seo.meta_description),
+            og_image: sea_orm::ActiveValue::Set(seo.og_image),
+            canonical_url: sea_orm::ActiveValue::Set(seo.canonical_url),
+            noindex: sea_orm::ActiveValue::Set(seo.noindex),
         };
 
-        let ent = post
-            .insert(&self._db_connection)
-            .await
-            .map_err(anyhow::Error::msg)?;
+        // The post and the cache generation bump go in together, so the
+        // public app never misses the invalidation of a stored post
+        let txn = self._db_connection.begin().await?;
+
+        let ent = post.insert(&txn).await.map_err(anyhow::Error::msg)?;
+        cache_generation::bump(&txn, &CacheScope::for_post_write(ent.id)).await?;
+
+        txn.commit().await?;
 
         let inserted_id = ent.id;
 
         Ok(inserted_id)
     }
 
-    pub async fn get_post(&self, post_id: i32) -> anyhow::Result<GetPostResponse, AppError> {
+    #[tracing::instrument(name = "db.update_post", skip(self, update, precondition))]
+    async fn update_post(
+        &self,
+        post_id: i32,
+        update: &UpdatePostRequest,
+        precondition: &(dyn for<'p> Fn(&'p GetPostResponse) -> bool + Send + Sync),
+    ) -> anyhow::Result<GetPostResponse, AppError> {
+        let txn = self._db_connection.begin().await.map_err(db_error)?;
+        let post = posts::Entity::find_by_id(post_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(db_error)?
+            .context("could not find post id in database")
+            .map_err(|e| AppError {
+                err_msg: e.to_string(),
+                status_code: StatusCode::NOT_FOUND,
+            })?;
+
+        if !precondition(&post.clone().into()) {
+            return Err(AppError {
+                err_msg: "post was modified since it was read".into(),
+                status_code: StatusCode::PRECONDITION_FAILED,
+            });
+        }
+
+        let seo = update
+            .seo
+            .as_ref()
+            .map(|seo| seo.apply(&PostSeo::from(&post)));
+        let mut post: posts::ActiveModel = post.into();
+        if let Some(title) = &update.title {
+            post.title = sea_orm::ActiveValue::Set(title.clone());
+        }
+        if let Some(content) = &update.content {
+            post.content = sea_orm::ActiveValue::Set(content.clone());
+        }
+        if let Some(excerpt) = &update.excerpt {
+            post.excerpt = sea_orm::ActiveValue::Set(excerpt.clone());
+        }
+        if let Some(seo) = seo {
+            post.meta_description = sea_orm::ActiveValue::Set(seo.meta_description);
+            post.og_image = sea_orm::ActiveValue::Set(seo.og_image);
+            post.canonical_url = sea_orm::ActiveValue::Set(seo.canonical_url);
+            post.noindex = sea_orm::ActiveValue::Set(seo.noindex);
+        }
+
+        let post = post.update(&txn).await.map_err(db_error)?;
+        cache_generation::bump(&txn, &CacheScope::for_post_write(post_id))
+            .await
+            .map_err(db_error)?;
+        txn.commit().await.map_err(db_error)?;
+
+        Ok(post.into())
+    }
+
+    #[tracing::instrument(name = "db.get_post", skip(self))]
+    async fn get_post(&self, post_id: i32) -> anyhow::Result<GetPostResponse, AppError> {
         // insert everything into db with ORM
         let post = posts::Entity::find_by_id(post_id)
             .one(&self._db_connection)
@@ -111,83 +435,442 @@ impl Database {
             .context("could not find post id in database")
             .map_err(|e| AppError {
                 err_msg: e.to_string(),
-                status_code: StatusCode::BAD_REQUEST,
+                status_code: StatusCode::NOT_FOUND,
             })?;
 
-        Ok(GetPostResponse {
-            content: post.content,
-            excerpt: post.excerpt,
-            post_id: post.id,
-            title: post.title,
+        Ok(post.into())
+    }
+
+    #[tracing::instrument(name = "db.get_posts", skip_all)]
+    async fn get_posts(&self, query: &PostsQuery) -> anyhow::Result<PostsPage, AppError> {
+        check_per_page(query.per_page)?;
+
+        let total = posts::Entity::find()
+            .count(&self._db_connection)
+            .await
+            .map_err(|e| AppError {
+                err_msg: e.to_string(),
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            })?;
+
+        let mut posts_query = posts::Entity::find();
+
+        // A cursor continues right after the post it points at, so rows
+        // deleted or added meanwhile don't shift the page boundaries
+        let offset = match &query.cursor {
+            Some(cursor) => {
+                let condition = PostCursor::decode(cursor, query.sort, query.order)
+                    .and_then(|cursor| cursor.condition())
+                    .ok_or(AppError {
+                        err_msg: "invalid pagination cursor".into(),
+                        status_code: StatusCode::BAD_REQUEST,
+                    })?;
+                posts_query = posts_query.filter(condition);
+                0
+            }
+            None => query.page.saturating_mul(query.per_page),
+        };
+
+        let order = match query.order {
+            SortOrder::Asc => Order::Asc,
+            SortOrder::Desc => Order::Desc,
+        };
+
+        // Break ties on the id so that equal timestamps keep a stable order.
+        // One extra row is fetched to find out whether there is a next page.
+        let mut items: Vec<GetPostResponse> = posts_query
+            .order_by(query.sort.column(), order.clone())
+            .order_by(posts::Column::Id, order)
+            .offset(offset)
+            .limit(query.per_page + 1)
+            .all(&self._db_connection)
+            .await
+            .map_err(|e| AppError {
+                err_msg: e.to_string(),
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            })?
+            .into_iter()
+            .map(GetPostResponse::from)
+            .collect();
+
+        let has_more = items.len() as u64 > query.per_page;
+        items.truncate(query.per_page as usize);
+
+        let next_cursor = match (has_more, items.last()) {
+            (true, Some(last)) => Some(PostCursor::after(last, query.sort, query.order).encode()),
+            _ => None,
+        };
+
+        Ok(PostsPage {
+            items,
+            total,
+            page: query.cursor.is_none().then_some(query.page),
+            per_page: query.per_page,
+            next_cursor,
         })
     }
 
-    pub async fn get_posts(
+    #[tracing::instrument(name = "db.count_indexable_posts", skip(self))]
+    async fn count_indexable_posts(&self) -> anyhow::Result<u64, AppError> {
+        posts::Entity::find()
+            .filter(posts::Column::Noindex.eq(false))
+            .count(&self._db_connection)
+            .await
+            .map_err(|e| AppError {
+                err_msg: e.to_string(),
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            })
+    }
+
+    #[tracing::instrument(name = "db.count_posts_by_status", skip(self))]
+    async fn count_posts_by_status(&self) -> Result<PostCounts, AppError> {
+        let noindex = posts::Entity::find()
+            .filter(posts::Column::Noindex.eq(true))
+            .count(&self._db_connection)
+            .await
+            .map_err(db_error)?;
+
+        Ok(PostCounts {
+            indexable: self.count_indexable_posts().await?,
+            noindex,
+        })
+    }
+
+    #[tracing::instrument(name = "db.get_post_stamps", skip(self))]
+    async fn get_post_stamps(
         &self,
-        offset: i32,
-        limit: i32,
-    ) -> anyhow::Result<Vec<GetPostResponse>, AppError> {
-        if offset.is_negative() {
-            return Err(AppError {
-                err_msg: "page number cannot be negative".into(),
-                status_code: StatusCode::BAD_REQUEST,
-            });
-        }
+        offset: u64,
+        limit: u64,
+    ) -> anyhow::Result<Vec<PostStamp>, AppError> {
+        let stamps: Vec<(i32, DateTimeUtc)> = posts::Entity::find()
+            .select_only()
+            .column(posts::Column::Id)
+            .column(posts::Column::UpdatedAt)
+            .filter(posts::Column::Noindex.eq(false))
+            .order_by(posts::Column::Id, Order::Asc)
+            .offset(offset)
+            .limit(limit)
+            .into_tuple()
+            .all(&self._db_connection)
+            .await
+            .map_err(|e| AppError {
+                err_msg: e.to_string(),
+                status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            })?;
 
-        let posts_query = posts::Entity::find();
-
-        let posts = if limit == -1 {
-            // Fetch all posts without pagination
-            posts_query.all(&self._db_connection).await
-        } else {
-            // Apply pagination
-            let start_offset = offset * limit;
-            let end_offset = (offset + 1) * limit;
-            posts_query
-                .cursor_by(posts::Column::Id)
-                .after(start_offset)
-                .before(end_offset)
-                .all(&self._db_connection)
-                .await
-        }
-        .map_err(|e| AppError {
-            err_msg: e.to_string(),
-            status_code: StatusCode::INTERNAL_SERVER_ERROR,
-        })?;
-
-        Ok(posts
-            .iter()
-            .map(|model| GetPostResponse {
-                post_id: model.id,
-                title: model.title.clone(),
-                content: model.content.clone(),
-                excerpt: model.excerpt.clone(),
+        Ok(stamps
+            .into_iter()
+            .map(|(post_id, updated_at)| PostStamp {
+                post_id,
+                updated_at,
             })
             .collect())
     }
 
-    pub async fn delete_post(&self, post_id: i32) -> anyhow::Result<DeletePostResponse, AppError> {
-        let post = posts::Entity::find_by_id(post_id)
-            .one(&self._db_connection)
+    #[tracing::instrument(name = "db.search_posts", skip_all)]
+    async fn search_posts(&self, query: &SearchQuery) -> anyhow::Result<SearchResults, AppError> {
+        check_per_page(query.per_page)?;
+
+        let terms = search::search_terms(&query.q);
+        if terms.is_empty() {
+            return Ok(SearchResults {
+                items: vec![],
+                total: 0,
+                page: query.page,
+                per_page: query.per_page,
+            });
+        }
+
+        // MariaDB uses the `ft_posts_search` FULLTEXT index and ranks by
+        // relevance; the other backends fall back to plain substring
+        // matching, newest first
+        let relevance = match self._db_connection.get_database_backend() {
+            DbBackend::MySql => Some(Expr::cust_with_values(
+                "MATCH(title, excerpt, content) AGAINST (? IN BOOLEAN MODE)",
+                [search::boolean_query(&terms)],
+            )),
+            DbBackend::Postgres | DbBackend::Sqlite => None,
+        };
+        let matches = match &relevance {
+            Some(relevance) => posts::Entity::find().filter(relevance.clone()),
+            None => posts::Entity::find().filter(search::substring_condition(&terms)),
+        };
+
+        let total = matches
+            .clone()
+            .count(&self._db_connection)
             .await
             .map_err(|e| AppError {
                 err_msg: e.to_string(),
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
-            })?
-            .context("could not find post id in database")
-            .map_err(|e| AppError {
-                err_msg: e.to_string(),
-                status_code: StatusCode::BAD_REQUEST,
             })?;
 
-        let _delete_res = post
-            .delete(&self._db_connection)
+        let mut ranked = matches;
+        if let Some(relevance) = relevance {
+            ranked = ranked.order_by(relevance, Order::Desc);
+        }
+        let items = ranked
+            .order_by(posts::Column::CreatedAt, Order::Desc)
+            .offset(query.page.saturating_mul(query.per_page))
+            .limit(query.per_page)
+            .all(&self._db_connection)
             .await
             .map_err(|e| AppError {
                 err_msg: e.to_string(),
                 status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            })?
+            .into_iter()
+            .map(|model| {
+                let post = GetPostResponse::from(model);
+                SearchHit {
+                    snippet: search::snippet(&post, &terms),
+                    post,
+                }
+            })
+            .collect();
+
+        Ok(SearchResults {
+            items,
+            total,
+            page: query.page,
+            per_page: query.per_page,
+        })
+    }
+
+    #[tracing::instrument(name = "db.delete_post", skip(self))]
+    async fn delete_post(&self, post_id: i32) -> anyhow::Result<DeletePostResponse, AppError> {
+        let post = posts::Entity::find_by_id(post_id)
+            .one(&self._db_connection)
+            .await
+            .map_err(db_error)?
+            .context("could not find post id in database")
+            .map_err(|e| AppError {
+                err_msg: e.to_string(),
+                status_code: StatusCode::NOT_FOUND,
             })?;
 
+        let txn = self._db_connection.begin().await.map_err(db_error)?;
+        let _delete_res = post.delete(&txn).await.map_err(db_error)?;
+        cache_generation::bump(&txn, &CacheScope::for_post_delete(post_id))
+            .await
+            .map_err(db_error)?;
+        txn.commit().await.map_err(db_error)?;
+
         Ok(DeletePostResponse { post_id })
     }
 }
+
+#[async_trait::async_trait]
+impl MenuRepository for Database {
+    #[tracing::instrument(name = "db.list_menus", skip(self))]
+    async fn list_menus(&self) -> Result<Vec<MenuSummary>, AppError> {
+        let menus = menus::Entity::find()
+            .order_by(menus::Column::Name, Order::Asc)
+            .all(&self._db_connection)
+            .await
+            .map_err(db_error)?;
+
+        Ok(menus
+            .into_iter()
+            .map(|menu| MenuSummary {
+                menu_id: menu.id,
+                name: menu.name,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "db.add_menu", skip_all)]
+    async fn add_menu(&self, menu_request: &AddMenuRequest) -> Result<i32, AppError> {
+        menu_request.validate()?;
+        let name = menu_request.name.trim();
+
+        let existing = menus::Entity::find()
+            .filter(menus::Column::Name.eq(name))
+            .one(&self._db_connection)
+            .await
+            .map_err(db_error)?;
+        if existing.is_some() {
+            return Err(menus::menu_name_taken(name));
+        }
+
+        // An empty menu shows nowhere yet, so no cached page is stale
+        let menu = menus::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            name: sea_orm::ActiveValue::Set(name.to_string()),
+        }
+        .insert(&self._db_connection)
+        .await
+        .map_err(db_error)?;
+
+        Ok(menu.id)
+    }
+
+    #[tracing::instrument(name = "db.get_menu", skip(self))]
+    async fn get_menu(&self, menu_id: i32) -> Result<MenuResponse, AppError> {
+        let menu = menus::Entity::find_by_id(menu_id)
+            .one(&self._db_connection)
+            .await
+            .map_err(db_error)?
+            .ok_or_else(menus::menu_not_found)?;
+
+        menu_response(&self._db_connection, menu).await
+    }
+
+    #[tracing::instrument(name = "db.find_menu", skip(self))]
+    async fn find_menu(&self, name: &str) -> Result<Option<MenuResponse>, AppError> {
+        let menu = menus::Entity::find()
+            .filter(menus::Column::Name.eq(name))
+            .one(&self._db_connection)
+            .await
+            .map_err(db_error)?;
+
+        match menu {
+            Some(menu) => Ok(Some(menu_response(&self._db_connection, menu).await?)),
+            None => Ok(None),
+        }
+    }
+
+    #[tracing::instrument(name = "db.delete_menu", skip(self))]
+    async fn delete_menu(&self, menu_id: i32) -> Result<DeleteMenuResponse, AppError> {
+        let txn = self._db_connection.begin().await.map_err(db_error)?;
+        let menu = menus::Entity::find_by_id(menu_id)
+            .one(&txn)
+            .await
+            .map_err(db_error)?
+            .ok_or_else(menus::menu_not_found)?;
+
+        // The items go along through their foreign key
+        menu.delete(&txn).await.map_err(db_error)?;
+        cache_generation::bump(&txn, &[CacheScope::Menu])
+            .await
+            .map_err(db_error)?;
+        txn.commit().await.map_err(db_error)?;
+
+        Ok(DeleteMenuResponse { menu_id })
+    }
+
+    #[tracing::instrument(name = "db.add_menu_item", skip(self, item_request))]
+    async fn add_menu_item(
+        &self,
+        menu_id: i32,
+        item_request: &MenuItemRequest,
+    ) -> Result<i32, AppError> {
+        item_request.validate()?;
+
+        let txn = self._db_connection.begin().await.map_err(db_error)?;
+        let items = menu_items_for_write(&txn, menu_id).await?;
+        item_request.check_parent(None, &items)?;
+        check_post_exists(&txn, item_request.post_id).await?;
+
+        let item = menu_items::ActiveModel {
+            id: sea_orm::ActiveValue::NotSet,
+            menu_id: sea_orm::ActiveValue::Set(menu_id),
+            ..menu_item_fields(item_request)
+        }
+        .insert(&txn)
+        .await
+        .map_err(db_error)?;
+        cache_generation::bump(&txn, &[CacheScope::Menu])
+            .await
+            .map_err(db_error)?;
+        txn.commit().await.map_err(db_error)?;
+
+        Ok(item.id)
+    }
+
+    #[tracing::instrument(name = "db.replace_menu_item", skip(self, item_request))]
+    async fn replace_menu_item(
+        &self,
+        menu_id: i32,
+        item_id: i32,
+        item_request: &MenuItemRequest,
+    ) -> Result<MenuResponse, AppError> {
+        item_request.validate()?;
+
+        let txn = self._db_connection.begin().await.map_err(db_error)?;
+        let items = menu_items_for_write(&txn, menu_id).await?;
+        if !items.iter().any(|item| item.id == item_id) {
+            return Err(menus::menu_item_not_found());
+        }
+        item_request.check_parent(Some(item_id), &items)?;
+        check_post_exists(&txn, item_request.post_id).await?;
+
+        menu_items::ActiveModel {
+            id: sea_orm::ActiveValue::Unchanged(item_id),
+            menu_id: sea_orm::ActiveValue::Unchanged(menu_id),
+            ..menu_item_fields(item_request)
+        }
+        .update(&txn)
+        .await
+        .map_err(db_error)?;
+        cache_generation::bump(&txn, &[CacheScope::Menu])
+            .await
+            .map_err(db_error)?;
+
+        let menu = menus::Entity::find_by_id(menu_id)
+            .one(&txn)
+            .await
+            .map_err(db_error)?
+            .ok_or_else(menus::menu_not_found)?;
+        let menu = menu_response(&txn, menu).await?;
+        txn.commit().await.map_err(db_error)?;
+
+        Ok(menu)
+    }
+
+    #[tracing::instrument(name = "db.delete_menu_item", skip(self))]
+    async fn delete_menu_item(
+        &self,
+        menu_id: i32,
+        item_id: i32,
+    ) -> Result<DeleteMenuItemResponse, AppError> {
+        let txn = self._db_connection.begin().await.map_err(db_error)?;
+        let item = menu_items::Entity::find_by_id(item_id)
+            .filter(menu_items::Column::MenuId.eq(menu_id))
+            .one(&txn)
+            .await
+            .map_err(db_error)?
+            .ok_or_else(menus::menu_item_not_found)?;
+
+        // Nested items go along through their foreign key
+        item.delete(&txn).await.map_err(db_error)?;
+        cache_generation::bump(&txn, &[CacheScope::Menu])
+            .await
+            .map_err(db_error)?;
+        txn.commit().await.map_err(db_error)?;
+
+        Ok(DeleteMenuItemResponse { item_id })
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageStatus for Database {
+    #[tracing::instrument(name = "db.schema_version", skip(self))]
+    async fn schema_version(&self) -> anyhow::Result<Option<String>> {
+        let applied = Migrator::get_applied_migrations(&self._db_connection).await?;
+        Ok(applied.last().map(|migration| migration.name().to_string()))
+    }
+
+    #[tracing::instrument(name = "db.check_ready", skip(self))]
+    async fn check_ready(&self) -> anyhow::Result<()> {
+        // Reading the migration status also shows the database is reachable
+        let pending = self.pending_migrations().await?;
+        if !pending.is_empty() {
+            anyhow::bail!("missing migrations: {}", pending.join(", "));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheGenerations for Database {
+    #[tracing::instrument(name = "db.latest_cache_generation", skip(self))]
+    async fn latest_cache_generation(&self) -> anyhow::Result<i64> {
+        Ok(cache_generation::latest(&self._db_connection).await?)
+    }
+
+    #[tracing::instrument(name = "db.stale_cache_scopes", skip(self))]
+    async fn stale_cache_scopes(&self, generation: i64) -> anyhow::Result<Vec<(CacheScope, i64)>> {
+        Ok(cache_generation::since(&self._db_connection, generation).await?)
+    }
+}
@@ -0,0 +1,484 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use http::StatusCode;
+
+use crate::{
+    check_per_page, menu_items, menus, posts, search, AddMenuRequest, AddPostRequest, AppError,
+    CacheGenerations, CacheScope, DeleteMenuItemResponse, DeleteMenuResponse, DeletePostResponse,
+    GetPostResponse, MenuItemRequest, MenuRepository, MenuResponse, MenuSummary, PostCounts,
+    PostCursor, PostRepository, PostSeo, PostStamp, PostsPage, PostsQuery, SearchHit, SearchQuery,
+    SearchResults, SortOrder, StorageStatus, UpdatePostRequest,
+};
+
+#[derive(Default)]
+struct MemoryState {
+    posts: BTreeMap<i32, posts::Model>,
+    last_id: i32,
+    menus: BTreeMap<i32, menus::Model>,
+    last_menu_id: i32,
+    menu_items: BTreeMap<i32, menu_items::Model>,
+    last_menu_item_id: i32,
+    generations: HashMap<CacheScope, i64>,
+    last_generation: i64,
+}
+
+impl MemoryState {
+    fn post(&mut self, post_id: i32) -> Result<&mut posts::Model, AppError> {
+        self.posts.get_mut(&post_id).ok_or_else(|| AppError {
+            err_msg: "could not find post id in database".into(),
+            status_code: StatusCode::NOT_FOUND,
+        })
+    }
+
+    fn bump(&mut self, scopes: &[CacheScope]) {
+        self.last_generation += 1;
+        for scope in scopes {
+            self.generations.insert(*scope, self.last_generation);
+        }
+    }
+
+    fn menu(&self, menu_id: i32) -> Result<&menus::Model, AppError> {
+        self.menus.get(&menu_id).ok_or_else(menus::menu_not_found)
+    }
+
+    fn menu_items_of(&self, menu_id: i32) -> Vec<menu_items::Model> {
+        self.menu_items
+            .values()
+            .filter(|item| item.menu_id == menu_id)
+            .cloned()
+            .collect()
+    }
+
+    fn menu_response(&self, menu: &menus::Model) -> MenuResponse {
+        MenuResponse {
+            menu_id: menu.id,
+            name: menu.name.clone(),
+            items: menus::build_tree(self.menu_items_of(menu.id)),
+        }
+    }
+
+    fn check_post_exists(&self, post_id: Option<i32>) -> Result<(), AppError> {
+        match post_id {
+            Some(post_id) if !self.posts.contains_key(&post_id) => Err(AppError {
+                err_msg: "could not find post id in database".into(),
+                status_code: StatusCode::BAD_REQUEST,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove an item along with everything nested below it, like the
+    /// foreign key does in the database
+    fn remove_menu_item(&mut self, item_id: i32) {
+        let children: Vec<i32> = self
+            .menu_items
+            .values()
+            .filter(|item| item.parent_id == Some(item_id))
+            .map(|item| item.id)
+            .collect();
+        for child in children {
+            self.remove_menu_item(child);
+        }
+        self.menu_items.remove(&item_id);
+    }
+}
+
+/// Post and menu store kept in process memory, for running the routers without a
+/// database. It answers like `Database`, except that search matches
+/// substrings and ranks newest first, and nothing outlives the process.
+/// Clones share the same posts.
+#[derive(Clone, Default)]
+pub struct MemoryRepository {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl PostRepository for MemoryRepository {
+    async fn add_post(&self, post_request: &AddPostRequest) -> anyhow::Result<i32> {
+        let seo = post_request.seo.normalized();
+        let now = Utc::now();
+
+        let mut state = self.state.lock().unwrap();
+        state.last_id += 1;
+        let post_id = state.last_id;
+        state.posts.insert(
+            post_id,
+            posts::Model {
+                id: post_id,
+                title: post_request.title.clone(),
+                content: post_request.content.clone(),
+                excerpt: post_request.excerpt.clone(),
+                created_at: now,
+                updated_at: now,
+                meta_description: seo.meta_description,
+                og_image: seo.og_image,
+                canonical_url: seo.canonical_url,
+                noindex: seo.noindex,
+            },
+        );
+        state.bump(&CacheScope::for_post_write(post_id));
+
+        Ok(post_id)
+    }
+
+    async fn update_post(
+        &self,
+        post_id: i32,
+        update: &UpdatePostRequest,
+        precondition: &(dyn for<'p> Fn(&'p GetPostResponse) -> bool + Send + Sync),
+    ) -> Result<GetPostResponse, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let post = state.post(post_id)?;
+
+        if !precondition(&post.clone().into()) {
+            return Err(AppError {
+                err_msg: "post was modified since it was read".into(),
+                status_code: StatusCode::PRECONDITION_FAILED,
+            });
+        }
+
+        if let Some(title) = &update.title {
+            post.title = title.clone();
+        }
+        if let Some(content) = &update.content {
+            post.content = content.clone();
+        }
+        if let Some(excerpt) = &update.excerpt {
+            post.excerpt = excerpt.clone();
+        }
+        if let Some(seo) = &update.seo {
+            let seo = seo.apply(&PostSeo::from(&*post));
+            post.meta_description = seo.meta_description;
+            post.og_image = seo.og_image;
+            post.canonical_url = seo.canonical_url;
+            post.noindex = seo.noindex;
+        }
+        post.updated_at = Utc::now();
+
+        let post = post.clone();
+        state.bump(&CacheScope::for_post_write(post_id));
+
+        Ok(post.into())
+    }
+
+    async fn get_post(&self, post_id: i32) -> Result<GetPostResponse, AppError> {
+        let mut state = self.state.lock().unwrap();
+        Ok(state.post(post_id)?.clone().into())
+    }
+
+    async fn get_posts(&self, query: &PostsQuery) -> Result<PostsPage, AppError> {
+        check_per_page(query.per_page)?;
+
+        let cursor = match &query.cursor {
+            Some(cursor) => Some(PostCursor::decode(cursor, query.sort, query.order).ok_or(
+                AppError {
+                    err_msg: "invalid pagination cursor".into(),
+                    status_code: StatusCode::BAD_REQUEST,
+                },
+            )?),
+            None => None,
+        };
+
+        let mut posts: Vec<GetPostResponse> = {
+            let state = self.state.lock().unwrap();
+            state.posts.values().cloned().map(Into::into).collect()
+        };
+        let total = posts.len() as u64;
+
+        // Same order as the database, ties broken on the id
+        posts.sort_by(|a, b| {
+            let ordering = query.sort.compare(a, b).then(a.post_id.cmp(&b.post_id));
+            match query.order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        });
+
+        let offset = match &cursor {
+            Some(cursor) => {
+                let mut follows = Vec::with_capacity(posts.len());
+                for post in &posts {
+                    follows.push(cursor.follows(post).ok_or(AppError {
+                        err_msg: "invalid pagination cursor".into(),
+                        status_code: StatusCode::BAD_REQUEST,
+                    })?);
+                }
+                follows
+                    .iter()
+                    .position(|follows| *follows)
+                    .unwrap_or(posts.len())
+            }
+            None => query.page.saturating_mul(query.per_page) as usize,
+        };
+
+        let rest: Vec<GetPostResponse> = posts.into_iter().skip(offset).collect();
+        let has_more = rest.len() as u64 > query.per_page;
+        let items: Vec<GetPostResponse> = rest.into_iter().take(query.per_page as usize).collect();
+
+        let next_cursor = match (has_more, items.last()) {
+            (true, Some(last)) => Some(PostCursor::after(last, query.sort, query.order).encode()),
+            _ => None,
+        };
+
+        Ok(PostsPage {
+            items,
+            total,
+            page: query.cursor.is_none().then_some(query.page),
+            per_page: query.per_page,
+            next_cursor,
+        })
+    }
+
+    async fn count_indexable_posts(&self) -> Result<u64, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.posts.values().filter(|post| !post.noindex).count() as u64)
+    }
+
+    async fn count_posts_by_status(&self) -> Result<PostCounts, AppError> {
+        let state = self.state.lock().unwrap();
+        let noindex = state.posts.values().filter(|post| post.noindex).count() as u64;
+        Ok(PostCounts {
+            indexable: state.posts.len() as u64 - noindex,
+            noindex,
+        })
+    }
+
+    async fn get_post_stamps(&self, offset: u64, limit: u64) -> Result<Vec<PostStamp>, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .posts
+            .values()
+            .filter(|post| !post.noindex)
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|post| PostStamp {
+                post_id: post.id,
+                updated_at: post.updated_at,
+            })
+            .collect())
+    }
+
+    async fn search_posts(&self, query: &SearchQuery) -> Result<SearchResults, AppError> {
+        check_per_page(query.per_page)?;
+
+        let terms = search::search_terms(&query.q);
+        let mut matches: Vec<GetPostResponse> = if terms.is_empty() {
+            vec![]
+        } else {
+            let state = self.state.lock().unwrap();
+            state
+                .posts
+                .values()
+                .filter(|post| {
+                    let text = [&post.title, &post.excerpt, &post.content]
+                        .map(|field| field.to_lowercase());
+                    terms
+                        .iter()
+                        .all(|term| text.iter().any(|field| field.contains(term.as_str())))
+                })
+                .cloned()
+                .map(Into::into)
+                .collect()
+        };
+        matches.sort_by_key(|post| Reverse(post.created_at));
+
+        let total = matches.len() as u64;
+        let items = matches
+            .into_iter()
+            .skip(query.page.saturating_mul(query.per_page) as usize)
+            .take(query.per_page as usize)
+            .map(|post| SearchHit {
+                snippet: search::snippet(&post, &terms),
+                post,
+            })
+            .collect();
+
+        Ok(SearchResults {
+            items,
+            total,
+            page: query.page,
+            per_page: query.per_page,
+        })
+    }
+
+    async fn delete_post(&self, post_id: i32) -> Result<DeletePostResponse, AppError> {
+        let mut state = self.state.lock().unwrap();
+        state.post(post_id)?;
+        state.posts.remove(&post_id);
+        for item in state.menu_items.values_mut() {
+            if item.post_id == Some(post_id) {
+                item.post_id = None;
+            }
+        }
+        state.bump(&CacheScope::for_post_delete(post_id));
+
+        Ok(DeletePostResponse { post_id })
+    }
+}
+
+#[async_trait::async_trait]
+impl MenuRepository for MemoryRepository {
+    async fn list_menus(&self) -> Result<Vec<MenuSummary>, AppError> {
+        let state = self.state.lock().unwrap();
+        let mut menus: Vec<MenuSummary> = state
+            .menus
+            .values()
+            .map(|menu| MenuSummary {
+                menu_id: menu.id,
+                name: menu.name.clone(),
+            })
+            .collect();
+        menus.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(menus)
+    }
+
+    async fn add_menu(&self, menu_request: &AddMenuRequest) -> Result<i32, AppError> {
+        menu_request.validate()?;
+        let name = menu_request.name.trim();
+
+        let mut state = self.state.lock().unwrap();
+        if state.menus.values().any(|menu| menu.name == name) {
+            return Err(menus::menu_name_taken(name));
+        }
+
+        state.last_menu_id += 1;
+        let menu_id = state.last_menu_id;
+        state.menus.insert(
+            menu_id,
+            menus::Model {
+                id: menu_id,
+                name: name.to_string(),
+            },
+        );
+
+        Ok(menu_id)
+    }
+
+    async fn get_menu(&self, menu_id: i32) -> Result<MenuResponse, AppError> {
+        let state = self.state.lock().unwrap();
+        let menu = state.menu(menu_id)?;
+
+        Ok(state.menu_response(menu))
+    }
+
+    async fn find_menu(&self, name: &str) -> Result<Option<MenuResponse>, AppError> {
+        let state = self.state.lock().unwrap();
+        let menu = state.menus.values().find(|menu| menu.name == name);
+
+        Ok(menu.map(|menu| state.menu_response(menu)))
+    }
+
+    async fn delete_menu(&self, menu_id: i32) -> Result<DeleteMenuResponse, AppError> {
+        let mut state = self.state.lock().unwrap();
+        state.menu(menu_id)?;
+        state.menus.remove(&menu_id);
+        state.menu_items.retain(|_, item| item.menu_id != menu_id);
+        state.bump(&[CacheScope::Menu]);
+
+        Ok(DeleteMenuResponse { menu_id })
+    }
+
+    async fn add_menu_item(
+        &self,
+        menu_id: i32,
+        item_request: &MenuItemRequest,
+    ) -> Result<i32, AppError> {
+        item_request.validate()?;
+
+        let mut state = self.state.lock().unwrap();
+        state.menu(menu_id)?;
+        item_request.check_parent(None, &state.menu_items_of(menu_id))?;
+        state.check_post_exists(item_request.post_id)?;
+
+        state.last_menu_item_id += 1;
+        let item_id = state.last_menu_item_id;
+        state
+            .menu_items
+            .insert(item_id, item_request.to_model(item_id, menu_id));
+        state.bump(&[CacheScope::Menu]);
+
+        Ok(item_id)
+    }
+
+    async fn replace_menu_item(
+        &self,
+        menu_id: i32,
+        item_id: i32,
+        item_request: &MenuItemRequest,
+    ) -> Result<MenuResponse, AppError> {
+        item_request.validate()?;
+
+        let mut state = self.state.lock().unwrap();
+        state.menu(menu_id)?;
+        let items = state.menu_items_of(menu_id);
+        if !items.iter().any(|item| item.id == item_id) {
+            return Err(menus::menu_item_not_found());
+        }
+        item_request.check_parent(Some(item_id), &items)?;
+        state.check_post_exists(item_request.post_id)?;
+
+        state
+            .menu_items
+            .insert(item_id, item_request.to_model(item_id, menu_id));
+        state.bump(&[CacheScope::Menu]);
+
+        let menu = state.menu(menu_id)?;
+        Ok(state.menu_response(menu))
+    }
+
+    async fn delete_menu_item(
+        &self,
+        menu_id: i32,
+        item_id: i32,
+    ) -> Result<DeleteMenuItemResponse, AppError> {
+        let mut state = self.state.lock().unwrap();
+        match state.menu_items.get(&item_id) {
+            Some(item) if item.menu_id == menu_id => {}
+            _ => return Err(menus::menu_item_not_found()),
+        }
+        state.remove_menu_item(item_id);
+        state.bump(&[CacheScope::Menu]);
+
+        Ok(DeleteMenuItemResponse { item_id })
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageStatus for MemoryRepository {
+    async fn schema_version(&self) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+
+    async fn check_ready(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheGenerations for MemoryRepository {
+    async fn latest_cache_generation(&self) -> anyhow::Result<i64> {
+        Ok(self.state.lock().unwrap().last_generation)
+    }
+
+    async fn stale_cache_scopes(&self, generation: i64) -> anyhow::Result<Vec<(CacheScope, i64)>> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .generations
+            .iter()
+            .filter(|(_, scope_generation)| **scope_generation > generation)
+            .map(|(scope, scope_generation)| (*scope, *scope_generation))
+            .collect())
+    }
+}
@@ -0,0 +1,27 @@
+use sea_orm::{
+    ActiveModelBehavior, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EnumIter,
+    PrimaryKeyTrait,
+};
+use serde::{Deserialize, Serialize};
+
+/// One entry of a menu. Items with a parent are nested below it, and
+/// siblings are ordered by `position`, then by id. An item links to a
+/// post or to a URL; one with neither is a heading for its children.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "menu_items")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub menu_id: i32,
+    pub parent_id: Option<i32>,
+    pub position: i32,
+    pub label: String,
+    pub title: Option<String>,
+    pub post_id: Option<i32>,
+    pub url: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+
+use http::StatusCode;
+use sea_orm::{
+    ActiveModelBehavior, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EnumIter,
+    PrimaryKeyTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{check_href, NavbarConfig},
+    menu_items, AppError,
+};
+
+/// Longest menu name, as stored in the `menus` table
+const MAX_MENU_NAME_LEN: usize = 64;
+
+/// Longest label, title or url of a menu item, as stored in the
+/// VARCHAR(255) columns of `menu_items`
+const MAX_MENU_ITEM_FIELD_LEN: usize = 255;
+
+/// A named menu, whose entries are the `menu_items` pointing at it
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "menus")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Deserialize)]
+pub struct AddMenuRequest {
+    // name the menu is looked up by, such as `main`
+    pub name: String,
+}
+
+#[derive(Serialize)]
+pub struct AddMenuResponse {
+    pub menu_id: i32,
+}
+
+#[derive(Serialize)]
+pub struct DeleteMenuResponse {
+    pub menu_id: i32,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MenuSummary {
+    pub menu_id: i32,
+    pub name: String,
+}
+
+/// A menu with its items nested below their parents
+#[derive(Clone, Serialize)]
+pub struct MenuResponse {
+    pub menu_id: i32,
+    pub name: String,
+    pub items: Vec<MenuItemResponse>,
+}
+
+/// Data to create a menu item, or to replace one as a whole
+#[derive(Deserialize)]
+pub struct MenuItemRequest {
+    // text of the link
+    pub label: String,
+    // title of the anchor, the label is used when unset
+    #[serde(default)]
+    pub title: Option<String>,
+    // item of the same menu to nest this one below
+    #[serde(default)]
+    pub parent_id: Option<i32>,
+    // order among the items with the same parent, lowest first
+    #[serde(default)]
+    pub position: i32,
+    // post to link to
+    #[serde(default)]
+    pub post_id: Option<i32>,
+    // or any other URL, with neither the item is a heading
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AddMenuItemResponse {
+    pub item_id: i32,
+}
+
+#[derive(Serialize)]
+pub struct DeleteMenuItemResponse {
+    pub item_id: i32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MenuItemResponse {
+    pub item_id: i32,
+    pub parent_id: Option<i32>,
+    pub position: i32,
+    pub label: String,
+    pub title: Option<String>,
+    pub post_id: Option<i32>,
+    pub url: Option<String>,
+    pub children: Vec<MenuItemResponse>,
+}
+
+/// Menu entry as the page templates render it, the same for menus from
+/// the database and for the `[navbar]` links of the config
+#[derive(Clone, Debug, Serialize)]
+pub struct MenuLink {
+    pub name: String,
+    // unset for headings
+    pub href: Option<String>,
+    pub title: String,
+    pub children: Vec<MenuLink>,
+}
+
+impl MenuLink {
+    /// Links of a menu from the database
+    pub fn from_menu(items: &[MenuItemResponse]) -> Vec<MenuLink> {
+        items
+            .iter()
+            .map(|item| MenuLink {
+                name: item.label.clone(),
+                href: item
+                    .post_id
+                    .map(|post_id| format!("/post/{}", post_id))
+                    .or_else(|| item.url.clone()),
+                title: item.title.clone().unwrap_or_else(|| item.label.clone()),
+                children: MenuLink::from_menu(&item.children),
+            })
+            .collect()
+    }
+
+    /// Links of the `[navbar]` config, which are never nested
+    pub fn from_config(navbar: &NavbarConfig) -> Vec<MenuLink> {
+        navbar
+            .links
+            .iter()
+            .map(|link| MenuLink {
+                name: link.name.clone(),
+                href: Some(link.href.clone()),
+                title: link.title.clone(),
+                children: Vec::new(),
+            })
+            .collect()
+    }
+}
+
+fn bad_request(err_msg: String) -> AppError {
+    AppError {
+        err_msg,
+        status_code: StatusCode::BAD_REQUEST,
+    }
+}
+
+fn not_found(err_msg: String) -> AppError {
+    AppError {
+        err_msg,
+        status_code: StatusCode::NOT_FOUND,
+    }
+}
+
+pub(crate) fn menu_not_found() -> AppError {
+    not_found("could not find menu id in database".into())
+}
+
+pub(crate) fn menu_item_not_found() -> AppError {
+    not_found("could not find menu item id in database".into())
+}
+
+pub(crate) fn menu_name_taken(name: &str) -> AppError {
+    AppError {
+        err_msg: format!("a menu named `{}` already exists", name),
+        status_code: StatusCode::CONFLICT,
+    }
+}
+
+impl AddMenuRequest {
+    /// Check the fields that don't need the database
+    pub(crate) fn validate(&self) -> Result<(), AppError> {
+        if self.name.trim().is_empty() {
+            return Err(bad_request("cannot have empty menu name".into()));
+        }
+        if self.name.len() > MAX_MENU_NAME_LEN {
+            return Err(bad_request(format!(
+                "menu name cannot be longer than {} bytes",
+                MAX_MENU_NAME_LEN
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl MenuItemRequest {
+    /// Check the fields that don't need the database
+    pub(crate) fn validate(&self) -> Result<(), AppError> {
+        if self.label.trim().is_empty() {
+            return Err(bad_request("cannot have empty menu item label".into()));
+        }
+        let fields = [
+            ("label", Some(self.label.trim())),
+            ("title", self.title.as_deref().map(str::trim)),
+            ("url", self.url.as_deref()),
+        ];
+        for (name, value) in fields {
+            if value.is_some_and(|v| v.chars().count() > MAX_MENU_ITEM_FIELD_LEN) {
+                return Err(bad_request(format!(
+                    "menu item {} cannot be longer than {} characters",
+                    name, MAX_MENU_ITEM_FIELD_LEN
+                )));
+            }
+        }
+        if self.post_id.is_some() && self.url.is_some() {
+            return Err(bad_request(
+                "a menu item links to a post_id or a url, not both".into(),
+            ));
+        }
+        if let Some(url) = &self.url {
+            check_href(url).map_err(|reason| bad_request(format!("menu item {}", reason)))?;
+        }
+
+        Ok(())
+    }
+
+    /// The stored item, with surrounding whitespace trimmed and an empty
+    /// title treated as unset so editors can clear it
+    pub(crate) fn to_model(&self, item_id: i32, menu_id: i32) -> menu_items::Model {
+        menu_items::Model {
+            id: item_id,
+            menu_id,
+            parent_id: self.parent_id,
+            position: self.position,
+            label: self.label.trim().to_string(),
+            title: self
+                .title
+                .as_ref()
+                .map(|title| title.trim().to_string())
+                .filter(|title| !title.is_empty()),
+            post_id: self.post_id,
+            url: self.url.clone(),
+        }
+    }
+
+    /// Check that the parent is an item of the same menu, and that
+    /// putting item `item_id` below it doesn't make the item its own
+    /// ancestor. `items` are all the items of the menu.
+    pub(crate) fn check_parent(
+        &self,
+        item_id: Option<i32>,
+        items: &[menu_items::Model],
+    ) -> Result<(), AppError> {
+        let parents: HashMap<i32, Option<i32>> =
+            items.iter().map(|item| (item.id, item.parent_id)).collect();
+
+        let mut ancestor = self.parent_id;
+        while let Some(id) = ancestor {
+            if Some(id) == item_id {
+                return Err(bad_request(
+                    "a menu item cannot be nested below itself".into(),
+                ));
+            }
+            ancestor = *parents
+                .get(&id)
+                .ok_or_else(|| bad_request(format!("menu item {} is not in this menu", id)))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Nest the items of one menu below their parents, siblings in order
+pub(crate) fn build_tree(mut items: Vec<menu_items::Model>) -> Vec<MenuItemResponse> {
+    items.sort_by_key(|item| (item.position, item.id));
+
+    let mut children: HashMap<Option<i32>, Vec<menu_items::Model>> = HashMap::new();
+    for item in items {
+        children.entry(item.parent_id).or_default().push(item);
+    }
+
+    fn nest(
+        parent_id: Option<i32>,
+        children: &mut HashMap<Option<i32>, Vec<menu_items::Model>>,
+    ) -> Vec<MenuItemResponse> {
+        children
+            .remove(&parent_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| MenuItemResponse {
+                children: nest(Some(item.id), children),
+                item_id: item.id,
+                parent_id: item.parent_id,
+                position: item.position,
+                label: item.label,
+                title: item.title,
+                post_id: item.post_id,
+                url: item.url,
+            })
+            .collect()
+    }
+
+    nest(None, &mut children)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: i32, parent_id: Option<i32>, position: i32) -> menu_items::Model {
+        menu_items::Model {
+            id,
+            menu_id: 1,
+            parent_id,
+            position,
+            label: format!("item {}", id),
+            title: None,
+            post_id: None,
+            url: None,
+        }
+    }
+
+    fn request(parent_id: Option<i32>) -> MenuItemRequest {
+        MenuItemRequest {
+            label: "item".into(),
+            title: None,
+            parent_id,
+            position: 0,
+            post_id: None,
+            url: None,
+        }
+    }
+
+    // 1 > 2 > 3, and 4 at the top level
+    fn items() -> Vec<menu_items::Model> {
+        vec![
+            item(1, None, 0),
+            item(2, Some(1), 0),
+            item(3, Some(2), 0),
+            item(4, None, 1),
+        ]
+    }
+
+    fn ids(tree: &[MenuItemResponse]) -> Vec<(i32, Vec<i32>)> {
+        tree.iter()
+            .map(|item| {
+                (
+                    item.item_id,
+                    item.children.iter().map(|child| child.item_id).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_parent_accepts_items_of_the_menu() {
+        let items = items();
+        assert!(request(None).check_parent(None, &items).is_ok());
+        assert!(request(Some(3)).check_parent(None, &items).is_ok());
+        // moving item 4 below the deepest item
+        assert!(request(Some(3)).check_parent(Some(4), &items).is_ok());
+        // moving item 3 up to the top level item
+        assert!(request(Some(1)).check_parent(Some(3), &items).is_ok());
+    }
+
+    #[test]
+    fn check_parent_rejects_cycles() {
+        let items = items();
+        let err = request(Some(1)).check_parent(Some(1), &items).unwrap_err();
+        assert_eq!(err.status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(err.err_msg, "a menu item cannot be nested below itself");
+
+        // below its child, or any item further down
+        for parent_id in [2, 3] {
+            let err = request(Some(parent_id))
+                .check_parent(Some(1), &items)
+                .unwrap_err();
+            assert_eq!(err.err_msg, "a menu item cannot be nested below itself");
+        }
+    }
+
+    #[test]
+    fn check_parent_rejects_parents_in_other_menus() {
+        // `items` only holds the items of the menu being edited
+        let err = request(Some(9)).check_parent(None, &items()).unwrap_err();
+        assert_eq!(err.status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(err.err_msg, "menu item 9 is not in this menu");
+
+        // nor may a parent chain leave the menu
+        let broken = vec![item(1, Some(9), 0)];
+        let err = request(Some(1)).check_parent(None, &broken).unwrap_err();
+        assert_eq!(err.err_msg, "menu item 9 is not in this menu");
+    }
+
+    #[test]
+    fn validate_rejects_fields_longer_than_their_column() {
+        let long = "é".repeat(MAX_MENU_ITEM_FIELD_LEN + 1);
+        let at_limit = "é".repeat(MAX_MENU_ITEM_FIELD_LEN);
+        let with_url = |url: String| MenuItemRequest {
+            url: Some(url),
+            ..request(None)
+        };
+
+        // characters count, not bytes, and the surrounding whitespace goes
+        let fits = MenuItemRequest {
+            label: format!(" {} ", at_limit),
+            title: Some(at_limit.clone()),
+            ..request(None)
+        };
+        assert!(fits.validate().is_ok());
+        assert!(with_url(format!("/{}", "a".repeat(254))).validate().is_ok());
+
+        let too_long = [
+            (
+                MenuItemRequest {
+                    label: long.clone(),
+                    ..request(None)
+                },
+                "label",
+            ),
+            (
+                MenuItemRequest {
+                    title: Some(long),
+                    ..request(None)
+                },
+                "title",
+            ),
+            (with_url(format!("/{}", "a".repeat(255))), "url"),
+        ];
+        for (request, name) in too_long {
+            let err = request.validate().unwrap_err();
+            assert_eq!(err.status_code, StatusCode::BAD_REQUEST);
+            assert_eq!(
+                err.err_msg,
+                format!("menu item {} cannot be longer than 255 characters", name)
+            );
+        }
+    }
+
+    #[test]
+    fn build_tree_nests_and_orders_items() {
+        let tree = build_tree(vec![
+            item(5, Some(1), 1),
+            item(4, None, 1),
+            item(2, Some(1), 0),
+            item(3, Some(1), 1),
+            item(1, None, 0),
+        ]);
+
+        // by position, then by id among equal positions
+        assert_eq!(ids(&tree), vec![(1, vec![2, 3, 5]), (4, vec![])]);
+        assert_eq!(tree[0].children[1].parent_id, Some(1));
+    }
+
+    #[test]
+    fn build_tree_nests_deeply() {
+        let tree = build_tree(items());
+        assert_eq!(ids(&tree), vec![(1, vec![2]), (4, vec![])]);
+        assert_eq!(ids(&tree[0].children), vec![(2, vec![3])]);
+        assert!(tree[0].children[0].children[0].children.is_empty());
+    }
+
+    #[test]
+    fn build_tree_leaves_out_orphans() {
+        // items whose parent is missing, or that only have each other as
+        // ancestors, can't be reached from the top level
+        let tree = build_tree(vec![
+            item(1, None, 0),
+            item(2, Some(9), 0),
+            item(3, Some(2), 0),
+            item(4, Some(5), 0),
+            item(5, Some(4), 0),
+        ]);
+        assert_eq!(ids(&tree), vec![(1, vec![])]);
+    }
+}
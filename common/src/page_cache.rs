@@ -0,0 +1,511 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use http::{header, HeaderValue, Method, StatusCode};
+use serde::Serialize;
+
+use crate::{config::CacheConfig, security_headers::replace_nonce, CspNonce};
+
+/// Response headers that are stored with a cached page and replayed on hits
+const CACHED_HEADERS: [header::HeaderName; 4] = [
+    header::CONTENT_TYPE,
+    header::ETAG,
+    header::LAST_MODIFIED,
+    header::CACHE_CONTROL,
+];
+
+/// Query parameters read by the cached pages. Requests carrying any other
+/// parameter bypass the cache, so made-up query strings can't fill it with
+/// copies of the same page.
+const KEY_PARAMS: [&str; 1] = ["page_num"];
+
+/// A rendered response body and the headers needed to replay it
+#[derive(Clone)]
+pub struct CachedPage {
+    pub headers: Vec<(header::HeaderName, HeaderValue)>,
+    pub body: Bytes,
+    // CSP nonce the body was rendered with, empty when it has none
+    pub nonce: String,
+}
+
+struct CacheEntry {
+    page: CachedPage,
+    inserted: Instant,
+    // value of `PageCache::clock` when the entry was last read
+    last_used: u64,
+}
+
+/// Limits of the cache, which can change while it is in use
+struct CacheLimits {
+    ttl: Duration,
+    max_entries: usize,
+    max_bytes: usize,
+}
+
+impl From<&CacheConfig> for CacheLimits {
+    fn from(config: &CacheConfig) -> Self {
+        CacheLimits {
+            ttl: Duration::from_secs(config.ttl_secs),
+            max_entries: config.max_entries,
+            max_bytes: config.max_bytes,
+        }
+    }
+}
+
+struct CacheEntries {
+    entries: HashMap<String, CacheEntry>,
+    bytes: usize,
+    limits: CacheLimits,
+}
+
+impl CacheEntries {
+    fn new(limits: CacheLimits) -> Self {
+        CacheEntries {
+            entries: HashMap::new(),
+            bytes: 0,
+            limits,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry.page.body.len();
+        }
+    }
+
+    /// Drop the least recently used entries until `extra` more bytes
+    /// and one more entry fit within the limits
+    fn make_room(&mut self, extra: usize) {
+        while !self.entries.is_empty()
+            && (self.entries.len() >= self.limits.max_entries
+                || self.bytes + extra > self.limits.max_bytes)
+        {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+                .unwrap();
+            self.remove(&oldest);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+/// Bounded in-memory cache of rendered pages, keyed by path and query.
+/// Entries expire after the configured TTL, and the least recently used
+/// ones are evicted once the entry count or total size limit is hit.
+pub struct PageCache {
+    enabled: AtomicBool,
+    entries: Mutex<CacheEntries>,
+    clock: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl PageCache {
+    pub fn new(enabled: bool, config: &CacheConfig) -> Self {
+        PageCache {
+            enabled: AtomicBool::new(enabled),
+            entries: Mutex::new(CacheEntries::new(config.into())),
+            clock: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Switch to new settings, dropping every cached page
+    pub fn reconfigure(&self, enabled: bool, config: &CacheConfig) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clear();
+        entries.limits = config.into();
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedPage> {
+        let mut entries = self.entries.lock().unwrap();
+        let tick = self.clock.fetch_add(1, Ordering::Relaxed);
+        let ttl = entries.limits.ttl;
+
+        let page = match entries.entries.get_mut(key) {
+            Some(entry) if entry.inserted.elapsed() < ttl => {
+                entry.last_used = tick;
+                Some(entry.page.clone())
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        match page {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        page
+    }
+
+    pub fn insert(&self, key: String, page: CachedPage) {
+        let size = page.body.len();
+        let mut entries = self.entries.lock().unwrap();
+        if size > entries.limits.max_bytes || entries.limits.max_entries == 0 {
+            return;
+        }
+
+        entries.remove(&key);
+        entries.make_room(size);
+
+        let tick = self.clock.fetch_add(1, Ordering::Relaxed);
+        entries.bytes += size;
+        entries.entries.insert(
+            key,
+            CacheEntry {
+                page,
+                inserted: Instant::now(),
+                last_used: tick,
+            },
+        );
+    }
+
+    fn invalidate_where(&self, stale: impl Fn(&str) -> bool) {
+        let mut entries = self.entries.lock().unwrap();
+        let keys: Vec<String> = entries
+            .entries
+            .keys()
+            .filter(|key| stale(key))
+            .cloned()
+            .collect();
+        for key in keys {
+            entries.remove(&key);
+        }
+    }
+
+    /// Drop every entry whose key starts with `prefix`
+    pub fn invalidate_prefix(&self, prefix: &str) {
+        self.invalidate_where(|key| key.starts_with(prefix));
+    }
+
+    /// Drop the cached page at `path`, with any query string
+    pub fn invalidate_path(&self, path: &str) {
+        self.invalidate_where(|key| {
+            key.strip_prefix(path)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('?'))
+        });
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries.lock().unwrap();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: entries.entries.len(),
+            bytes: entries.bytes,
+        }
+    }
+}
+
+/// Replay a cached page, with its nonce swapped for the one of the
+/// current response so its inline scripts still run
+fn cached_response(page: CachedPage, nonce: &CspNonce) -> Response {
+    let body = replace_nonce(&page.body, &page.nonce, &nonce.0);
+    let mut response = Response::new(Body::from(body));
+    for (name, value) in page.headers {
+        response.headers_mut().insert(name, value);
+    }
+    response
+        .headers_mut()
+        .insert("x-cache", HeaderValue::from_static("HIT"));
+    response
+}
+
+/// Whether every parameter of the query string is one of `KEY_PARAMS`
+fn has_only_key_params(query: Option<&str>) -> bool {
+    query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .all(|param| {
+            let name = param.split_once('=').map_or(param, |(name, _)| name);
+            KEY_PARAMS.contains(&name)
+        })
+}
+
+/// Middleware serving `GET` requests from the page cache, and storing
+/// successful responses of the wrapped routes in it
+pub async fn page_cache_layer(
+    State(cache): State<Arc<PageCache>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !cache.is_enabled()
+        || request.method() != Method::GET
+        || !has_only_key_params(request.uri().query())
+    {
+        return next.run(request).await;
+    }
+
+    let key = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let nonce = request
+        .extensions()
+        .get::<CspNonce>()
+        .cloned()
+        .unwrap_or_default();
+    if let Some(page) = cache.get(&key) {
+        return cached_response(page, &nonce);
+    }
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(_) => {
+            return Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+                .unwrap()
+        }
+    };
+
+    cache.insert(
+        key,
+        CachedPage {
+            headers: CACHED_HEADERS
+                .iter()
+                .filter_map(|name| {
+                    parts
+                        .headers
+                        .get(name)
+                        .map(|value| (name.clone(), value.clone()))
+                })
+                .collect(),
+            body: body.clone(),
+            nonce: nonce.0,
+        },
+    );
+
+    parts
+        .headers
+        .insert("x-cache", HeaderValue::from_static("MISS"));
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware, response::Html, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn config(max_entries: usize, max_bytes: usize) -> CacheConfig {
+        CacheConfig {
+            max_entries,
+            max_bytes,
+            ..CacheConfig::default()
+        }
+    }
+
+    fn page(body: &str) -> CachedPage {
+        CachedPage {
+            headers: Vec::new(),
+            body: Bytes::from(body.to_string()),
+            nonce: String::new(),
+        }
+    }
+
+    fn cached(cache: &PageCache, key: &str) -> bool {
+        cache.get(key).is_some()
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entry() {
+        let cache = PageCache::new(true, &config(2, 1024));
+        cache.insert("/a".to_string(), page("a"));
+        cache.insert("/b".to_string(), page("b"));
+        // reading `/a` makes `/b` the least recently used
+        assert!(cached(&cache, "/a"));
+        cache.insert("/c".to_string(), page("c"));
+
+        assert!(!cached(&cache, "/b"));
+        assert!(cached(&cache, "/a"));
+        assert!(cached(&cache, "/c"));
+        assert_eq!(cache.stats().entries, 2);
+    }
+
+    #[test]
+    fn evicts_until_the_bytes_fit() {
+        let cache = PageCache::new(true, &config(10, 10));
+        cache.insert("/a".to_string(), page("aaaa"));
+        cache.insert("/b".to_string(), page("bbbb"));
+        cache.insert("/c".to_string(), page("cccccc"));
+
+        // dropping `/a` is enough to fit `/c`
+        assert!(!cached(&cache, "/a"));
+        assert!(cached(&cache, "/b"));
+        assert!(cached(&cache, "/c"));
+        assert_eq!(cache.stats().bytes, 10);
+
+        // a page bigger than the whole cache is not stored
+        cache.insert("/d".to_string(), page("ddddddddddd"));
+        assert!(!cached(&cache, "/d"));
+        assert!(cached(&cache, "/c"));
+    }
+
+    #[test]
+    fn expires_entries_after_ttl() {
+        let cache = PageCache::new(true, &CacheConfig::default());
+        cache.entries.lock().unwrap().limits.ttl = Duration::from_millis(20);
+        cache.insert("/a".to_string(), page("a"));
+        assert!(cached(&cache, "/a"));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(!cached(&cache, "/a"));
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.bytes), (0, 0));
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+    }
+
+    async fn nonce_page(CspNonce(nonce): CspNonce) -> Html<String> {
+        Html(format!("<script nonce=\"{nonce}\"></script>"))
+    }
+
+    async fn missing(CspNonce(nonce): CspNonce) -> (StatusCode, Html<String>) {
+        (StatusCode::NOT_FOUND, Html(format!("missing {nonce}")))
+    }
+
+    fn router(cache: Arc<PageCache>) -> Router {
+        Router::new()
+            .route("/", get(nonce_page))
+            .route("/missing", get(missing))
+            .route_layer(middleware::from_fn_with_state(cache, page_cache_layer))
+    }
+
+    async fn fetch(
+        router: &Router,
+        path: &str,
+        nonce: &str,
+    ) -> (StatusCode, Option<String>, String) {
+        let mut request = Request::builder().uri(path).body(Body::empty()).unwrap();
+        request.extensions_mut().insert(CspNonce(nonce.to_string()));
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let x_cache = response
+            .headers()
+            .get("x-cache")
+            .map(|value| value.to_str().unwrap().to_string());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, x_cache, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn stores_only_ok_responses() {
+        let cache = Arc::new(PageCache::new(true, &CacheConfig::default()));
+        let router = router(cache.clone());
+
+        for nonce in ["first", "second"] {
+            let (status, x_cache, body) = fetch(&router, "/missing", nonce).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            assert_eq!(x_cache, None);
+            assert_eq!(body, format!("missing {nonce}"));
+        }
+        assert_eq!(cache.stats().entries, 0);
+
+        let (status, x_cache, _) = fetch(&router, "/", "first").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(x_cache.as_deref(), Some("MISS"));
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[tokio::test]
+    async fn hits_carry_the_nonce_of_their_request() {
+        let cache = Arc::new(PageCache::new(true, &CacheConfig::default()));
+        let router = router(cache.clone());
+
+        let (_, x_cache, first) = fetch(&router, "/", "first-nonce").await;
+        assert_eq!(x_cache.as_deref(), Some("MISS"));
+        assert_eq!(first, "<script nonce=\"first-nonce\"></script>");
+
+        let (status, x_cache, second) = fetch(&router, "/", "second-nonce").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(x_cache.as_deref(), Some("HIT"));
+        assert_eq!(second, "<script nonce=\"second-nonce\"></script>");
+
+        // the stored page keeps its own nonce, so later hits still get theirs
+        let (_, x_cache, third) = fetch(&router, "/", "third-nonce").await;
+        assert_eq!(x_cache.as_deref(), Some("HIT"));
+        assert_eq!(third, "<script nonce=\"third-nonce\"></script>");
+    }
+
+    #[tokio::test]
+    async fn other_query_parameters_bypass_the_cache() {
+        let cache = Arc::new(PageCache::new(true, &CacheConfig::default()));
+        let router = router(cache.clone());
+
+        for path in ["/?x=1", "/?x=2", "/?page_num=2&x=3", "/?page%5Fnum=2"] {
+            let (status, x_cache, _) = fetch(&router, path, "nonce").await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(x_cache, None);
+        }
+        assert_eq!(cache.stats().entries, 0);
+
+        for expected in ["MISS", "HIT"] {
+            let (_, x_cache, _) = fetch(&router, "/?page_num=2", "nonce").await;
+            assert_eq!(x_cache.as_deref(), Some(expected));
+        }
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[tokio::test]
+    async fn disabled_cache_stores_nothing() {
+        let cache = Arc::new(PageCache::new(false, &CacheConfig::default()));
+        let router = router(cache.clone());
+
+        for _ in 0..2 {
+            let (_, x_cache, _) = fetch(&router, "/", "nonce").await;
+            assert_eq!(x_cache, None);
+        }
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
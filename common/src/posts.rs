@@ -1,8 +1,12 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Utc};
 use sea_orm::{
-    ActiveModelBehavior, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EnumIter,
+    prelude::DateTimeUtc, ActiveModelBehavior, ActiveValue, ColumnTrait, Condition,
+    ConnectionTrait, DbErr, DeriveEntityModel, DerivePrimaryKey, DeriveRelation, EnumIter,
     PrimaryKeyTrait,
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "posts")]
@@ -13,6 +17,14 @@ pub struct Model {
     pub title: String,
     pub content: String,
     pub excerpt: String,
+    #[serde(skip_deserializing)]
+    pub created_at: DateTimeUtc,
+    #[serde(skip_deserializing)]
+    pub updated_at: DateTimeUtc,
+    pub meta_description: Option<String>,
+    pub og_image: Option<String>,
+    pub canonical_url: Option<String>,
+    pub noindex: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -24,7 +36,23 @@ pub enum Relation {}
 //     }
 // }
 
-impl ActiveModelBehavior for ActiveModel {}
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // Keep the timestamps up to date on every write, so callers
+    // never have to set them by hand
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = Utc::now();
+        if insert {
+            self.created_at = ActiveValue::Set(now);
+        }
+        self.updated_at = ActiveValue::Set(now);
+
+        Ok(self)
+    }
+}
 
 
 // Urchin stuff only
@@ -34,6 +62,95 @@ pub struct AddPostRequest {
     pub title: String,
     pub content: String,
     pub excerpt: String,
+    #[serde(default)]
+    pub seo: PostSeo,
+}
+
+/// Partial update of a post, fields left out are kept as they are
+#[derive(Deserialize)]
+pub struct UpdatePostRequest {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub excerpt: Option<String>,
+    pub seo: Option<UpdatePostSeo>,
+}
+
+/// Tell a field sent as `null` apart from one that was left out
+fn sent<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Partial update of the SEO metadata of a post. Fields left out are
+/// kept, while `null` or an empty string clears a text field.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct UpdatePostSeo {
+    #[serde(default, deserialize_with = "sent")]
+    pub meta_description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "sent")]
+    pub og_image: Option<Option<String>>,
+    #[serde(default, deserialize_with = "sent")]
+    pub canonical_url: Option<Option<String>>,
+    #[serde(default)]
+    pub noindex: Option<bool>,
+}
+
+impl UpdatePostSeo {
+    /// `current` with the fields that were sent replaced
+    pub fn apply(&self, current: &PostSeo) -> PostSeo {
+        let pick = |sent: &Option<Option<String>>, current: &Option<String>| match sent {
+            Some(value) => value.clone(),
+            None => current.clone(),
+        };
+
+        PostSeo {
+            meta_description: pick(&self.meta_description, &current.meta_description),
+            og_image: pick(&self.og_image, &current.og_image),
+            canonical_url: pick(&self.canonical_url, &current.canonical_url),
+            noindex: self.noindex.unwrap_or(current.noindex),
+        }
+        .normalized()
+    }
+}
+
+/// Search engine and social sharing metadata of a post
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PostSeo {
+    // description for search results and link previews,
+    // the excerpt is used when unset
+    #[serde(default)]
+    pub meta_description: Option<String>,
+    // image shown in link previews
+    #[serde(default)]
+    pub og_image: Option<String>,
+    // canonical URL, when the post is also published elsewhere
+    #[serde(default)]
+    pub canonical_url: Option<String>,
+    // ask search engines not to index the post
+    #[serde(default)]
+    pub noindex: bool,
+}
+
+impl PostSeo {
+    /// Treat empty strings as unset, so editors can clear a field
+    pub(crate) fn normalized(&self) -> PostSeo {
+        let non_empty = |value: &Option<String>| {
+            value
+                .as_ref()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        PostSeo {
+            meta_description: non_empty(&self.meta_description),
+            og_image: non_empty(&self.og_image),
+            canonical_url: non_empty(&self.canonical_url),
+            noindex: self.noindex,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -53,4 +170,500 @@ pub struct GetPostResponse
     pub title: String,
     pub content: String,
     pub excerpt: String,
-}
\ No newline at end of file
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+    pub seo: PostSeo,
+}
+
+impl From<&Model> for PostSeo {
+    fn from(model: &Model) -> Self {
+        PostSeo {
+            meta_description: model.meta_description.clone(),
+            og_image: model.og_image.clone(),
+            canonical_url: model.canonical_url.clone(),
+            noindex: model.noindex,
+        }
+    }
+}
+
+impl From<Model> for GetPostResponse {
+    fn from(model: Model) -> Self {
+        let seo = PostSeo::from(&model);
+        GetPostResponse {
+            post_id: model.id,
+            title: model.title,
+            content: model.content,
+            excerpt: model.excerpt,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+            seo,
+        }
+    }
+}
+
+impl GetPostResponse {
+    /// Strong ETag of the post as served by the admin API
+    pub fn etag(&self) -> String {
+        crate::etag_for(&serde_json::to_vec(self).unwrap())
+    }
+}
+
+/// Id and last modification time of a post, enough to list it in
+/// a sitemap without loading its content
+#[derive(Clone, Debug, Serialize)]
+pub struct PostStamp {
+    pub post_id: i32,
+    pub updated_at: DateTimeUtc,
+}
+
+/// Number of posts in each state. Posts have no draft or published
+/// status, all of them are live; they only differ in whether search
+/// engines may index them.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct PostCounts {
+    pub indexable: u64,
+    pub noindex: u64,
+}
+
+/// Column that post listings can be sorted by
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostSortField {
+    Id,
+    Title,
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl PostSortField {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PostSortField::Id => "id",
+            PostSortField::Title => "title",
+            PostSortField::CreatedAt => "created_at",
+            PostSortField::UpdatedAt => "updated_at",
+        }
+    }
+
+    /// Order of two posts on this field alone, as the database sorts them
+    #[cfg_attr(not(feature = "memory"), allow(dead_code))]
+    pub(crate) fn compare(&self, a: &GetPostResponse, b: &GetPostResponse) -> Ordering {
+        match self {
+            PostSortField::Id => a.post_id.cmp(&b.post_id),
+            PostSortField::Title => a.title.cmp(&b.title),
+            PostSortField::CreatedAt => a.created_at.cmp(&b.created_at),
+            PostSortField::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+        }
+    }
+
+    pub(crate) fn column(&self) -> Column {
+        match self {
+            PostSortField::Id => Column::Id,
+            PostSortField::Title => Column::Title,
+            PostSortField::CreatedAt => Column::CreatedAt,
+            PostSortField::UpdatedAt => Column::UpdatedAt,
+        }
+    }
+}
+
+/// Direction of a post listing, newest-first by default
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+fn default_per_page() -> u64 {
+    10
+}
+
+/// Which page of posts to fetch. Uses offset pagination on `page`,
+/// unless a `cursor` from a previous page's `next_cursor` is given,
+/// in which case the listing continues right after that post.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PostsQuery {
+    // zero-based page number, ignored when `cursor` is set
+    #[serde(default)]
+    pub page: u64,
+    // number of posts per page
+    #[serde(default = "default_per_page", alias = "limit")]
+    pub per_page: u64,
+    // opaque keyset cursor
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub sort: PostSortField,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+impl Default for PostsQuery {
+    fn default() -> Self {
+        PostsQuery {
+            page: 0,
+            per_page: default_per_page(),
+            cursor: None,
+            sort: PostSortField::default(),
+            order: SortOrder::default(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct PostsPage {
+    pub items: Vec<GetPostResponse>,
+    // number of posts in the whole listing
+    pub total: u64,
+    // the page that was fetched, `None` for cursor queries
+    pub page: Option<u64>,
+    pub per_page: u64,
+    // cursor for the following page, `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Position of the last post of a page in a sorted listing. It is
+/// handed out hex-encoded, so clients can treat it as opaque.
+pub(crate) struct PostCursor {
+    sort: PostSortField,
+    order: SortOrder,
+    id: i32,
+    // the sort column value of the post, unused when sorting by id
+    value: String,
+}
+
+impl PostCursor {
+    pub(crate) fn after(post: &GetPostResponse, sort: PostSortField, order: SortOrder) -> Self {
+        let value = match sort {
+            PostSortField::Id => String::new(),
+            PostSortField::Title => post.title.clone(),
+            PostSortField::CreatedAt => post.created_at.to_rfc3339(),
+            PostSortField::UpdatedAt => post.updated_at.to_rfc3339(),
+        };
+
+        PostCursor {
+            sort,
+            order,
+            id: post.post_id,
+            value,
+        }
+    }
+
+    pub(crate) fn encode(&self) -> String {
+        hex::encode(format!(
+            "{}:{}:{}:{}",
+            self.sort.as_str(),
+            self.order.as_str(),
+            self.id,
+            self.value
+        ))
+    }
+
+    /// Decodes a cursor, only accepting it for the listing it came from
+    pub(crate) fn decode(cursor: &str, sort: PostSortField, order: SortOrder) -> Option<Self> {
+        let decoded = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+        let mut parts = decoded.splitn(4, ':');
+
+        if parts.next()? != sort.as_str() || parts.next()? != order.as_str() {
+            return None;
+        }
+        let id = parts.next()?.parse().ok()?;
+        let value = parts.next()?.to_string();
+
+        Some(PostCursor {
+            sort,
+            order,
+            id,
+            value,
+        })
+    }
+
+    /// Whether `post` comes after this cursor, the in-memory counterpart
+    /// of `condition`
+    #[cfg_attr(not(feature = "memory"), allow(dead_code))]
+    pub(crate) fn follows(&self, post: &GetPostResponse) -> Option<bool> {
+        let timestamp = || {
+            DateTime::parse_from_rfc3339(&self.value)
+                .ok()
+                .map(|value| value.with_timezone(&Utc))
+        };
+
+        let ordering = match self.sort {
+            PostSortField::Id => Ordering::Equal,
+            PostSortField::Title => post.title.as_str().cmp(self.value.as_str()),
+            PostSortField::CreatedAt => post.created_at.cmp(&timestamp()?),
+            PostSortField::UpdatedAt => post.updated_at.cmp(&timestamp()?),
+        }
+        .then(post.post_id.cmp(&self.id));
+
+        Some(match self.order {
+            SortOrder::Asc => ordering.is_gt(),
+            SortOrder::Desc => ordering.is_lt(),
+        })
+    }
+
+    /// Condition selecting the posts that come after this cursor
+    pub(crate) fn condition(&self) -> Option<Condition> {
+        let value: sea_orm::Value = match self.sort {
+            PostSortField::Id => self.id.into(),
+            PostSortField::Title => self.value.clone().into(),
+            PostSortField::CreatedAt | PostSortField::UpdatedAt => {
+                DateTime::parse_from_rfc3339(&self.value)
+                    .ok()?
+                    .with_timezone(&Utc)
+                    .into()
+            }
+        };
+
+        let column = self.sort.column();
+        let (past_value, past_id) = match self.order {
+            SortOrder::Asc => (column.gt(value.clone()), Column::Id.gt(self.id)),
+            SortOrder::Desc => (column.lt(value.clone()), Column::Id.lt(self.id)),
+        };
+
+        Some(
+            Condition::any()
+                .add(past_value)
+                .add(Condition::all().add(column.eq(value)).add(past_id)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const SORTS: [PostSortField; 4] = [
+        PostSortField::Id,
+        PostSortField::Title,
+        PostSortField::CreatedAt,
+        PostSortField::UpdatedAt,
+    ];
+    const ORDERS: [SortOrder; 2] = [SortOrder::Asc, SortOrder::Desc];
+
+    fn post(post_id: i32, title: &str, day: u32) -> GetPostResponse {
+        let at = Utc.with_ymd_and_hms(2024, 1, day, 12, 0, 0).unwrap();
+        GetPostResponse {
+            post_id,
+            title: title.into(),
+            content: String::new(),
+            excerpt: String::new(),
+            created_at: at,
+            updated_at: at,
+            seo: PostSeo::default(),
+        }
+    }
+
+    /// Posts 1, 3 and 5 tie on every sort key but the id
+    fn posts() -> Vec<GetPostResponse> {
+        vec![
+            post(1, "b", 2),
+            post(2, "a", 1),
+            post(3, "b", 2),
+            post(4, "c", 3),
+            post(5, "b", 2),
+        ]
+    }
+
+    /// Ids of `posts` in the order of a listing, with ties broken on the
+    /// id like the database does
+    fn listing(sort: PostSortField, order: SortOrder) -> Vec<i32> {
+        let mut posts = posts();
+        posts.sort_by(|a, b| {
+            let ordering = sort.compare(a, b).then(a.post_id.cmp(&b.post_id));
+            match order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        });
+        posts.iter().map(|post| post.post_id).collect()
+    }
+
+    fn find(post_id: i32) -> GetPostResponse {
+        posts()
+            .into_iter()
+            .find(|post| post.post_id == post_id)
+            .unwrap()
+    }
+
+    #[test]
+    fn cursors_decode_to_what_was_encoded() {
+        for (sort, order) in SORTS.into_iter().flat_map(|s| ORDERS.map(|o| (s, o))) {
+            for post in posts().iter().chain([&post(9, "with: colons", 4)]) {
+                let cursor = PostCursor::after(post, sort, order);
+                let decoded = PostCursor::decode(&cursor.encode(), sort, order).unwrap();
+
+                assert_eq!(decoded.sort, sort);
+                assert_eq!(decoded.order, order);
+                assert_eq!(decoded.id, post.post_id);
+                assert_eq!(decoded.value, cursor.value);
+                assert!(decoded.condition().is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn cursors_only_decode_for_their_own_listing() {
+        let cursor = PostCursor::after(&post(1, "b", 2), PostSortField::Title, SortOrder::Asc);
+        let encoded = cursor.encode();
+
+        assert!(PostCursor::decode(&encoded, PostSortField::Title, SortOrder::Desc).is_none());
+        assert!(PostCursor::decode(&encoded, PostSortField::CreatedAt, SortOrder::Asc).is_none());
+    }
+
+    #[test]
+    fn garbage_cursors_are_rejected() {
+        for cursor in [
+            String::new(),
+            "not hex".into(),
+            "abc".into(),
+            hex::encode([0xff, 0xfe, 0xfd]),
+            hex::encode("title:asc"),
+            hex::encode("title:asc:one:b"),
+            hex::encode("nope:asc:1:b"),
+            hex::encode("title:sideways:1:b"),
+        ] {
+            assert!(
+                PostCursor::decode(&cursor, PostSortField::Title, SortOrder::Asc).is_none(),
+                "{}",
+                cursor
+            );
+        }
+    }
+
+    #[test]
+    fn tampered_timestamps_are_rejected() {
+        let cursor = hex::encode("created_at:desc:1:yesterday");
+        let cursor =
+            PostCursor::decode(&cursor, PostSortField::CreatedAt, SortOrder::Desc).unwrap();
+
+        assert!(cursor.condition().is_none());
+        assert!(cursor.follows(&post(1, "b", 2)).is_none());
+    }
+
+    #[test]
+    fn posts_tied_on_the_sort_key_follow_in_id_order() {
+        for (sort, order) in SORTS.into_iter().flat_map(|s| ORDERS.map(|o| (s, o))) {
+            let listing = listing(sort, order);
+            for (position, post_id) in listing.iter().enumerate() {
+                let cursor = PostCursor::after(&find(*post_id), sort, order);
+                let following: Vec<i32> = listing
+                    .iter()
+                    .copied()
+                    .filter(|post_id| cursor.follows(&find(*post_id)).unwrap())
+                    .collect();
+
+                assert_eq!(
+                    following,
+                    listing[position + 1..],
+                    "after post {} by {:?} {:?}",
+                    post_id,
+                    sort,
+                    order
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn database_continues_after_the_cursor() {
+        use sea_orm::{EntityTrait, QueryFilter, QueryOrder};
+
+        let db = crate::test_database::connect("post-cursor").await;
+        // inserted as is, without the hooks that set the timestamps to now
+        let rows = posts().into_iter().map(|post| ActiveModel {
+            id: ActiveValue::Set(post.post_id),
+            title: ActiveValue::Set(post.title),
+            content: ActiveValue::Set(post.content),
+            excerpt: ActiveValue::Set(post.excerpt),
+            created_at: ActiveValue::Set(post.created_at),
+            updated_at: ActiveValue::Set(post.updated_at),
+            meta_description: ActiveValue::Set(None),
+            og_image: ActiveValue::Set(None),
+            canonical_url: ActiveValue::Set(None),
+            noindex: ActiveValue::Set(false),
+        });
+        Entity::insert_many(rows).exec(&db).await.unwrap();
+
+        for (sort, order) in SORTS.into_iter().flat_map(|s| ORDERS.map(|o| (s, o))) {
+            let db_order = match order {
+                SortOrder::Asc => sea_orm::Order::Asc,
+                SortOrder::Desc => sea_orm::Order::Desc,
+            };
+            let listing = listing(sort, order);
+            for (position, post_id) in listing.iter().enumerate() {
+                let cursor = PostCursor::after(&find(*post_id), sort, order);
+                let following: Vec<i32> = Entity::find()
+                    .filter(cursor.condition().unwrap())
+                    .order_by(sort.column(), db_order.clone())
+                    .order_by(Column::Id, db_order.clone())
+                    .all(&db)
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(|post| post.id)
+                    .collect();
+
+                assert_eq!(
+                    following,
+                    listing[position + 1..],
+                    "after post {} by {:?} {:?}",
+                    post_id,
+                    sort,
+                    order
+                );
+            }
+        }
+    }
+
+    fn seo() -> PostSeo {
+        PostSeo {
+            meta_description: Some("about".into()),
+            og_image: Some("/image.png".into()),
+            canonical_url: Some("https://example.com/post".into()),
+            noindex: true,
+        }
+    }
+
+    fn seo_update(json: &str) -> UpdatePostSeo {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn seo_updates_keep_the_fields_left_out() {
+        let current = seo();
+
+        let updated = seo_update(r#"{"noindex": false}"#).apply(&current);
+        assert!(!updated.noindex);
+        assert_eq!(updated.meta_description, current.meta_description);
+        assert_eq!(updated.og_image, current.og_image);
+        assert_eq!(updated.canonical_url, current.canonical_url);
+
+        let updated = seo_update(r#"{"meta_description": "new"}"#).apply(&current);
+        assert_eq!(updated.meta_description.as_deref(), Some("new"));
+        assert_eq!(updated.og_image, current.og_image);
+        assert!(updated.noindex);
+    }
+
+    #[test]
+    fn seo_updates_clear_fields_sent_empty_or_null() {
+        let updated = seo_update(r#"{"og_image": null, "canonical_url": "  "}"#).apply(&seo());
+        assert_eq!(updated.og_image, None);
+        assert_eq!(updated.canonical_url, None);
+        assert_eq!(updated.meta_description.as_deref(), Some("about"));
+    }
+}
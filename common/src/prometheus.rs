@@ -0,0 +1,151 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use http::header;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+use crate::{bind_listener, Database, MetricsConfig, PageCache, PostRepository};
+
+/// Upper bounds, in seconds, of the buckets of every `*_seconds` histogram
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// Start recording metrics, returning the handle `/metrics` renders them
+/// with. Until this is called recording them does nothing.
+fn install_metrics_recorder() -> anyhow::Result<PrometheusHandle> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".into()), &LATENCY_BUCKETS)?
+        .install_recorder()?;
+    Ok(handle)
+}
+
+/// Count the requests of `router` and time them, by route, method and
+/// status. Routes are labelled with their pattern such as `/post/:id`,
+/// so the number of series stays bounded.
+pub fn with_request_metrics(router: Router) -> Router {
+    router.layer(middleware::from_fn(track_request))
+}
+
+async fn track_request(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".into());
+    let method = request.method().to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let labels = [
+        ("route", route),
+        ("method", method),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    metrics::counter!("http_requests_total", &labels).increment(1);
+    metrics::histogram!("http_request_duration_seconds", &labels).record(started.elapsed());
+    response
+}
+
+/// Record how long rendering the page template `template` took
+pub fn record_template_render(template: &'static str, elapsed: Duration) {
+    metrics::histogram!("template_render_duration_seconds", "template" => template).record(elapsed);
+}
+
+/// What `/metrics` reads when it is scraped, on top of what was recorded
+#[derive(Clone)]
+struct MetricsState {
+    handle: PrometheusHandle,
+    database: Database,
+    page_cache: Option<Arc<PageCache>>,
+}
+
+/// Start recording metrics and serve them at `/metrics` on `port` of
+/// `config.address`, in the background. Fails when the port can't be
+/// bound. The page cache is left out by the apps that have none.
+pub async fn spawn_metrics_server(
+    config: &MetricsConfig,
+    port: u16,
+    database: Database,
+    page_cache: Option<Arc<PageCache>>,
+) -> anyhow::Result<()> {
+    let listener = bind_listener(&config.address, port)
+        .await
+        .context("could not start the metrics listener")?;
+    let router = metrics_router(install_metrics_recorder()?, database, page_cache);
+
+    tracing::info!(
+        "serving metrics on http://{}/metrics",
+        listener.local_addr()?
+    );
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router).await {
+            tracing::error!("the metrics listener stopped: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+fn metrics_router(
+    handle: PrometheusHandle,
+    database: Database,
+    page_cache: Option<Arc<PageCache>>,
+) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(MetricsState {
+            handle,
+            database,
+            page_cache,
+        })
+}
+
+async fn metrics_handler(State(state): State<MetricsState>) -> impl IntoResponse {
+    if let Some(pool) = state.database.pool_status() {
+        metrics::gauge!("db_pool_connections", "state" => "idle").set(pool.idle);
+        metrics::gauge!("db_pool_connections", "state" => "in_use")
+            .set(pool.size.saturating_sub(pool.idle));
+        metrics::gauge!("db_pool_max_connections").set(pool.max);
+    }
+
+    // A failed count keeps the last values, the pool gauges above show
+    // whether the database is the problem
+    match state.database.count_posts_by_status().await {
+        Ok(counts) => {
+            metrics::gauge!("cms_posts", "status" => "indexable").set(counts.indexable as f64);
+            metrics::gauge!("cms_posts", "status" => "noindex").set(counts.noindex as f64);
+        }
+        Err(e) => tracing::warn!("could not count the posts for the metrics: {}", e.err_msg),
+    }
+
+    if let Some(cache) = &state.page_cache {
+        let stats = cache.stats();
+        let lookups = stats.hits + stats.misses;
+        metrics::counter!("page_cache_hits_total").absolute(stats.hits);
+        metrics::counter!("page_cache_misses_total").absolute(stats.misses);
+        metrics::gauge!("page_cache_hit_ratio").set(match lookups {
+            0 => 0.0,
+            lookups => stats.hits as f64 / lookups as f64,
+        });
+        metrics::gauge!("page_cache_entries").set(stats.entries as f64);
+        metrics::gauge!("page_cache_bytes").set(stats.bytes as f64);
+    }
+
+    state.handle.run_upkeep();
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.handle.render(),
+    )
+}
@@ -0,0 +1,118 @@
+use crate::{
+    AddMenuRequest, AddPostRequest, AppError, CacheScope, DeleteMenuItemResponse,
+    DeleteMenuResponse, DeletePostResponse, GetPostResponse, MenuItemRequest, MenuResponse,
+    MenuSummary, PostCounts, PostStamp, PostsPage, PostsQuery, SearchQuery, SearchResults,
+    UpdatePostRequest,
+};
+
+/// Everything the apps' handlers need from the storage. `Database` is the
+/// real implementation; with the `memory` feature, `MemoryRepository`
+/// keeps everything in process so the routers can run without one.
+pub trait Repository: PostRepository + MenuRepository + StorageStatus + CacheGenerations {}
+
+impl<T> Repository for T where T: PostRepository + MenuRepository + StorageStatus + CacheGenerations {}
+
+/// Storage of the posts
+#[async_trait::async_trait]
+pub trait PostRepository: Send + Sync {
+    /// Store a new post, returning its id
+    async fn add_post(&self, post_request: &AddPostRequest) -> anyhow::Result<i32>;
+
+    /// Apply a partial update to a post. `precondition` is checked against
+    /// the post as stored, with the row locked, so a client can make sure
+    /// nobody changed it since it was read; the update is refused with
+    /// `412 Precondition Failed` when it does not hold.
+    async fn update_post(
+        &self,
+        post_id: i32,
+        update: &UpdatePostRequest,
+        precondition: &(dyn for<'p> Fn(&'p GetPostResponse) -> bool + Send + Sync),
+    ) -> Result<GetPostResponse, AppError>;
+
+    async fn get_post(&self, post_id: i32) -> Result<GetPostResponse, AppError>;
+
+    /// One page of posts in the requested order
+    async fn get_posts(&self, query: &PostsQuery) -> Result<PostsPage, AppError>;
+
+    /// Number of posts search engines may index
+    async fn count_indexable_posts(&self) -> Result<u64, AppError>;
+
+    /// Number of posts by whether search engines may index them
+    async fn count_posts_by_status(&self) -> Result<PostCounts, AppError>;
+
+    /// Ids and modification times of the posts search engines may
+    /// index, in id order, without the post bodies
+    async fn get_post_stamps(&self, offset: u64, limit: u64) -> Result<Vec<PostStamp>, AppError>;
+
+    /// Full-text search over the title, excerpt and content of all posts,
+    /// best matches first
+    async fn search_posts(&self, query: &SearchQuery) -> Result<SearchResults, AppError>;
+
+    /// Delete a post. Menu items linking to it stay, as headings.
+    async fn delete_post(&self, post_id: i32) -> Result<DeletePostResponse, AppError>;
+}
+
+/// Navigation menus and their nested items
+#[async_trait::async_trait]
+pub trait MenuRepository: Send + Sync {
+    /// All menus, by name
+    async fn list_menus(&self) -> Result<Vec<MenuSummary>, AppError>;
+
+    /// Create an empty menu, returning its id
+    async fn add_menu(&self, menu_request: &AddMenuRequest) -> Result<i32, AppError>;
+
+    async fn get_menu(&self, menu_id: i32) -> Result<MenuResponse, AppError>;
+
+    /// The menu called `name`, `None` when there is none
+    async fn find_menu(&self, name: &str) -> Result<Option<MenuResponse>, AppError>;
+
+    /// Delete a menu along with all of its items
+    async fn delete_menu(&self, menu_id: i32) -> Result<DeleteMenuResponse, AppError>;
+
+    /// Add an item to a menu, returning its id
+    async fn add_menu_item(
+        &self,
+        menu_id: i32,
+        item_request: &MenuItemRequest,
+    ) -> Result<i32, AppError>;
+
+    /// Replace all fields of a menu item, which moves it when the parent
+    /// or position changed. Returns the menu as it is afterwards.
+    async fn replace_menu_item(
+        &self,
+        menu_id: i32,
+        item_id: i32,
+        item_request: &MenuItemRequest,
+    ) -> Result<MenuResponse, AppError>;
+
+    /// Delete a menu item along with the items nested below it
+    async fn delete_menu_item(
+        &self,
+        menu_id: i32,
+        item_id: i32,
+    ) -> Result<DeleteMenuItemResponse, AppError>;
+}
+
+/// Health of the storage itself, for startup checks and `/readyz`
+#[async_trait::async_trait]
+pub trait StorageStatus: Send + Sync {
+    /// Name of the newest migration applied to the storage, `None` when
+    /// it has no schema to migrate
+    async fn schema_version(&self) -> anyhow::Result<Option<String>>;
+
+    /// Fail when the storage can't serve requests: it is unreachable, or
+    /// its schema lacks migrations this build expects
+    async fn check_ready(&self) -> anyhow::Result<()>;
+}
+
+/// Generations that writes bump, which the public app polls to evict
+/// the cached pages they made stale
+#[async_trait::async_trait]
+pub trait CacheGenerations: Send + Sync {
+    /// Newest cache generation, where polling for invalidations starts
+    async fn latest_cache_generation(&self) -> anyhow::Result<i64>;
+
+    /// Cache scopes that were bumped after `generation`, with the
+    /// generation they are at now
+    async fn stale_cache_scopes(&self, generation: i64) -> anyhow::Result<Vec<(CacheScope, i64)>>;
+}
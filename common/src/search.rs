@@ -0,0 +1,304 @@
+use pulldown_cmark::{Event, Parser};
+use sea_orm::{
+    sea_query::{Expr, Func},
+    Condition,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{posts, GetPostResponse};
+
+/// Number of characters of context kept on each side of the first match
+const SNIPPET_CONTEXT: usize = 80;
+
+fn default_per_page() -> u64 {
+    10
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SearchQuery {
+    // the text typed into the search box
+    #[serde(default)]
+    pub q: String,
+    // zero-based page number
+    #[serde(default)]
+    pub page: u64,
+    // number of results per page
+    #[serde(default = "default_per_page")]
+    pub per_page: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SearchHit {
+    pub post: GetPostResponse,
+    // HTML excerpt of the post around the first match, with the
+    // matching words wrapped in `<mark>`
+    pub snippet: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SearchResults {
+    pub items: Vec<SearchHit>,
+    // number of posts matching the query
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+}
+
+/// Split a user query into lowercase words, dropping duplicates and
+/// anything that is not a letter or digit
+pub(crate) fn search_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in query.split(|c: char| !c.is_alphanumeric()) {
+        let word = word.to_lowercase();
+        if !word.is_empty() && !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+/// Condition requiring every term to appear in the title, excerpt or
+/// content, for backends without a FULLTEXT index. Terms only hold
+/// letters and digits, so they need no escaping in `LIKE` patterns.
+pub(crate) fn substring_condition(terms: &[String]) -> Condition {
+    terms.iter().fold(Condition::all(), |condition, term| {
+        let pattern = format!("%{}%", term);
+        let in_column =
+            |column: posts::Column| Expr::expr(Func::lower(Expr::col(column))).like(&pattern);
+
+        condition.add(
+            Condition::any()
+                .add(in_column(posts::Column::Title))
+                .add(in_column(posts::Column::Excerpt))
+                .add(in_column(posts::Column::Content)),
+        )
+    })
+}
+
+/// Build a MariaDB boolean-mode FULLTEXT query requiring every term,
+/// each one matched as a prefix
+pub(crate) fn boolean_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("+{}*", term))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Render markdown to the plain text a reader would see
+fn markdown_to_text(markdown: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak | Event::End(_) => text.push(' '),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape_html(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#x27;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn matches_term(word: &str, terms: &[String]) -> bool {
+    let word = word.to_lowercase();
+    terms.iter().any(|term| word.starts_with(term.as_str()))
+}
+
+/// Split text into alternating runs of word and non-word characters
+fn word_runs(text: &str) -> Vec<(bool, &str)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut in_word = None;
+
+    for (i, c) in text.char_indices() {
+        let is_word = c.is_alphanumeric();
+        match in_word {
+            Some(current) if current != is_word => {
+                runs.push((current, &text[start..i]));
+                start = i;
+            }
+            _ => {}
+        }
+        in_word = Some(is_word);
+    }
+    if let Some(current) = in_word {
+        runs.push((current, &text[start..]));
+    }
+
+    runs
+}
+
+/// Build an HTML-safe snippet of `markdown` around the first word that
+/// matches one of `terms`. Returns `None` if no word matches.
+fn snippet_of(markdown: &str, terms: &[String]) -> Option<String> {
+    let text = markdown_to_text(markdown);
+    let runs = word_runs(&text);
+    let first_match = runs
+        .iter()
+        .position(|(is_word, run)| *is_word && matches_term(run, terms))?;
+
+    // Walk outwards from the match until enough context is collected
+    let mut start = first_match;
+    let mut before = 0;
+    while start > 0 && before < SNIPPET_CONTEXT {
+        start -= 1;
+        before += runs[start].1.chars().count();
+    }
+    let mut end = first_match + 1;
+    let mut after = 0;
+    while end < runs.len() && after < SNIPPET_CONTEXT {
+        after += runs[end].1.chars().count();
+        end += 1;
+    }
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push_str("&hellip;");
+    }
+    for (is_word, run) in &runs[start..end] {
+        if *is_word && matches_term(run, terms) {
+            snippet.push_str("<mark>");
+            escape_html(run, &mut snippet);
+            snippet.push_str("</mark>");
+        } else {
+            escape_html(run, &mut snippet);
+        }
+    }
+    if end < runs.len() {
+        snippet.push_str("&hellip;");
+    }
+
+    Some(snippet)
+}
+
+/// Snippet shown for a search hit: the first match in the content,
+/// then in the excerpt, falling back to the start of the excerpt
+pub(crate) fn snippet(post: &GetPostResponse, terms: &[String]) -> String {
+    snippet_of(&post.content, terms)
+        .or_else(|| snippet_of(&post.excerpt, terms))
+        .unwrap_or_else(|| {
+            let mut snippet = String::new();
+            let text = markdown_to_text(&post.excerpt);
+            let shortened: String = text.chars().take(2 * SNIPPET_CONTEXT).collect();
+            escape_html(&shortened, &mut snippet);
+            if shortened.len() < text.len() {
+                snippet.push_str("&hellip;");
+            }
+            snippet
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::prelude::DateTimeUtc;
+
+    use super::*;
+
+    fn terms(query: &str) -> Vec<String> {
+        search_terms(query)
+    }
+
+    fn post(content: &str, excerpt: &str) -> GetPostResponse {
+        GetPostResponse {
+            post_id: 1,
+            title: "title".into(),
+            content: content.into(),
+            excerpt: excerpt.into(),
+            created_at: DateTimeUtc::default(),
+            updated_at: DateTimeUtc::default(),
+            seo: Default::default(),
+        }
+    }
+
+    #[test]
+    fn search_terms_are_lowercase_words() {
+        assert_eq!(terms("Rust, rust & ÜBER-fast!"), ["rust", "über", "fast"]);
+        assert!(terms(" -- ").is_empty());
+    }
+
+    #[test]
+    fn matches_at_the_start_and_end_get_no_ellipsis() {
+        assert_eq!(
+            snippet_of("Über alles", &terms("über")).unwrap(),
+            "<mark>Über</mark> alles"
+        );
+        assert_eq!(
+            snippet_of("alles übermorgen", &terms("über")).unwrap(),
+            "alles <mark>übermorgen</mark>"
+        );
+        assert_eq!(
+            snippet_of("über", &terms("über")).unwrap(),
+            "<mark>über</mark>"
+        );
+        assert_eq!(snippet_of("nothing here", &terms("über")), None);
+    }
+
+    #[test]
+    fn multibyte_text_is_cut_between_characters() {
+        let before = "ééé ".repeat(30);
+        let after = " ñññ".repeat(30);
+        let snippet = snippet_of(&format!("{}日本語{}", before, after), &terms("日本語")).unwrap();
+
+        // context is counted in characters, not bytes
+        let expected = format!(
+            "&hellip;{}<mark>日本語</mark>{}&hellip;",
+            "ééé ".repeat(20),
+            " ñññ".repeat(20)
+        );
+        assert_eq!(snippet, expected);
+    }
+
+    #[test]
+    fn snippets_escape_html() {
+        assert_eq!(
+            snippet_of("Tom & \"Jerry\" isn't `<script>`", &terms("tom")).unwrap(),
+            "<mark>Tom</mark> &amp; &quot;Jerry&quot; isn&#x27;t &lt;script&gt;"
+        );
+        assert_eq!(
+            snippet_of("`if a<b&&c` then", &terms("b")).unwrap(),
+            "if a&lt;<mark>b</mark>&amp;&amp;c then"
+        );
+    }
+
+    #[test]
+    fn every_matching_word_in_the_snippet_is_marked() {
+        assert_eq!(
+            snippet_of("*Rust* and rusty tools", &terms("rust tool")).unwrap(),
+            "<mark>Rust</mark> and <mark>rusty</mark> <mark>tools</mark>"
+        );
+    }
+
+    #[test]
+    fn snippet_falls_back_to_the_excerpt() {
+        let hit = post("nothing relevant", "a `<match>` & more");
+        assert_eq!(
+            snippet(&hit, &terms("match")),
+            "a &lt;<mark>match</mark>&gt; &amp; more"
+        );
+
+        // without any match the start of the excerpt is shown, shortened
+        // by characters
+        let long = post("", &"é".repeat(2 * SNIPPET_CONTEXT + 1));
+        assert_eq!(
+            snippet(&long, &terms("nothing")),
+            format!("{}&hellip;", "é".repeat(2 * SNIPPET_CONTEXT))
+        );
+        let short = post("", &"é".repeat(2 * SNIPPET_CONTEXT));
+        assert_eq!(
+            snippet(&short, &terms("nothing")),
+            "é".repeat(2 * SNIPPET_CONTEXT)
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use std::fmt::{self, Debug, Display};
+
+use serde::{Deserialize, Serialize, Serializer};
+
+/// Placeholder printed instead of secret values
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+/// Config value that must never end up in logs, such as a password.
+/// `Debug`, `Display` and `Serialize` all print a placeholder, and the
+/// actual value is only available through `expose`.
+#[derive(Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    /// The actual value, for the one place that needs it
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl<T> Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
@@ -0,0 +1,124 @@
+use std::{convert::Infallible, sync::Arc};
+
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequestParts, Request, State},
+    http::request::Parts,
+    middleware::{self, Next},
+    response::Response,
+    Router,
+};
+use http::{header, HeaderName, HeaderValue, StatusCode};
+
+use crate::config::SecurityHeadersConfig;
+
+/// Stands for the nonce of the response in the configured CSP
+pub(crate) const NONCE_PLACEHOLDER: &str = "{nonce}";
+
+/// Nonce of the Content-Security-Policy of the current response, for the
+/// templates to put on their inline scripts. Empty when the policy has
+/// no nonce, so handlers can take it either way.
+#[derive(Clone, Debug, Default)]
+pub struct CspNonce(pub String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for CspNonce {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<CspNonce>()
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+/// The headers to set, ready to be copied into responses
+struct SecurityHeaders {
+    fixed: Vec<(HeaderName, HeaderValue)>,
+    // policy with the nonce placeholder still in, made for each response
+    csp_template: Option<String>,
+}
+
+impl SecurityHeaders {
+    fn new(config: &SecurityHeadersConfig) -> Self {
+        let mut headers = SecurityHeaders {
+            fixed: Vec::new(),
+            csp_template: None,
+        };
+
+        // Invalid values were refused when the config was validated
+        for (name, value) in config.headers() {
+            if value.is_empty() {
+                continue;
+            }
+            if name == header::CONTENT_SECURITY_POLICY && value.contains(NONCE_PLACEHOLDER) {
+                headers.csp_template = Some(value.to_string());
+            } else if let Ok(value) = HeaderValue::from_str(value) {
+                headers.fixed.push((name, value));
+            }
+        }
+
+        headers
+    }
+}
+
+fn new_nonce() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+/// `body` with every occurrence of the nonce `from` replaced by `to`.
+/// Bodies that aren't text can't hold a nonce and are returned as is.
+pub(crate) fn replace_nonce(body: &Bytes, from: &str, to: &str) -> Bytes {
+    match std::str::from_utf8(body) {
+        Ok(text) if !from.is_empty() && text.contains(from) => Bytes::from(text.replace(from, to)),
+        _ => body.clone(),
+    }
+}
+
+async fn security_headers_layer(
+    State(headers): State<Arc<SecurityHeaders>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let nonce = headers.csp_template.as_ref().map(|_| new_nonce());
+    if let Some(nonce) = &nonce {
+        request.extensions_mut().insert(CspNonce(nonce.clone()));
+    }
+
+    let mut response = next.run(request).await;
+    let response_headers = response.headers_mut();
+    for (name, value) in &headers.fixed {
+        response_headers
+            .entry(name)
+            .or_insert_with(|| value.clone());
+    }
+
+    // A 304 has the client show the page it stored, which names the nonce
+    // of the policy stored with it. A new policy would block its scripts.
+    if response.status() != StatusCode::NOT_MODIFIED {
+        if let (Some(template), Some(nonce)) = (&headers.csp_template, &nonce) {
+            if let Ok(csp) = HeaderValue::from_str(&template.replace(NONCE_PLACEHOLDER, nonce)) {
+                response
+                    .headers_mut()
+                    .entry(header::CONTENT_SECURITY_POLICY)
+                    .or_insert(csp);
+            }
+        }
+    }
+
+    response
+}
+
+/// Add the configured security headers to the responses of `router`.
+/// Handlers get the nonce of the Content-Security-Policy through the
+/// `CspNonce` extractor.
+pub fn with_security_headers(router: Router, config: &SecurityHeadersConfig) -> Router {
+    let headers = Arc::new(SecurityHeaders::new(config));
+    router.layer(middleware::from_fn_with_state(
+        headers,
+        security_headers_layer,
+    ))
+}
@@ -0,0 +1,268 @@
+use std::{
+    fs::{self, Permissions},
+    io::ErrorKind,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use anyhow::Context;
+use axum::{Json, Router};
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use http::StatusCode;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::{conn::auto, graceful::GracefulShutdown},
+    service::TowerToHyperService,
+};
+use serde::Serialize;
+use tokio::{
+    net::{TcpListener, UnixListener},
+    signal::unix::{signal, SignalKind},
+};
+
+use crate::AppError;
+
+/// Set once the process is asked to stop, so `/readyz` turns load
+/// balancers away while the requests in flight drain
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Where an app accepts its connections
+pub enum AppListener {
+    Tcp(TcpListener),
+    /// The socket file is removed once the server stops
+    Unix(UnixListener, PathBuf),
+}
+
+/// Listen on `port` of `address`, naming both when that fails
+pub async fn bind_listener(address: &str, port: u16) -> anyhow::Result<TcpListener> {
+    TcpListener::bind((address, port))
+        .await
+        .with_context(|| format!("could not listen on {}:{}", address, port))
+}
+
+/// Listen on the Unix socket at `socket` when given, with `socket_mode`
+/// permissions, else on `port` of `address`
+pub async fn bind_app(
+    address: &str,
+    port: u16,
+    socket: Option<&str>,
+    socket_mode: u32,
+) -> anyhow::Result<AppListener> {
+    let Some(socket) = socket else {
+        return Ok(AppListener::Tcp(bind_listener(address, port).await?));
+    };
+
+    // A socket left behind by a server that didn't stop cleanly would make
+    // binding fail, while any other kind of file there is not ours to remove
+    match fs::symlink_metadata(socket) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(socket)
+            .with_context(|| format!("could not remove the old socket {}", socket))?,
+        Ok(_) => anyhow::bail!("could not listen on {}: not a socket", socket),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("could not listen on {}", socket)),
+    }
+    let listener =
+        UnixListener::bind(socket).with_context(|| format!("could not listen on {}", socket))?;
+    fs::set_permissions(socket, Permissions::from_mode(socket_mode))
+        .with_context(|| format!("could not set the permissions of {}", socket))?;
+
+    Ok(AppListener::Unix(listener, PathBuf::from(socket)))
+}
+
+/// Resolve on SIGTERM, which orchestrators send to stop a process, or on
+/// Ctrl+C
+async fn shutdown_signal() {
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => Some(terminate),
+        Err(e) => {
+            tracing::warn!("could not listen for SIGTERM: {}", e);
+            None
+        }
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        Some(_) = async { terminate.as_mut()?.recv().await } => {}
+    }
+    SHUTTING_DOWN.store(true, Ordering::Relaxed);
+}
+
+/// Resolve `delay` after the shutdown signal, serving with a failing
+/// `/readyz` in between
+async fn stop_requested(delay: Duration) {
+    shutdown_signal().await;
+    if !delay.is_zero() {
+        tracing::info!("not ready anymore, shutting down in {}s", delay.as_secs());
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Serve `router` until the process is asked to stop, over HTTPS when
+/// `tls` is given and the listener is TCP. HTTP/2 is offered next to
+/// HTTP/1.1. Once asked to stop, `/readyz` fails and requests are still
+/// served for `shutdown_delay`. Then new connections are refused, and the
+/// requests in flight get up to `drain_timeout` to finish before the
+/// remaining ones are dropped.
+pub async fn serve(
+    listener: AppListener,
+    router: Router,
+    shutdown_delay: Duration,
+    drain_timeout: Duration,
+    tls: Option<RustlsConfig>,
+) -> anyhow::Result<()> {
+    let listener = match listener {
+        AppListener::Tcp(listener) => listener,
+        AppListener::Unix(listener, path) => {
+            let served = serve_unix(&listener, &path, router, shutdown_delay, drain_timeout).await;
+            if let Err(e) = fs::remove_file(&path) {
+                tracing::warn!("could not remove the socket {}: {}", path.display(), e);
+            }
+            return served;
+        }
+    };
+
+    let scheme = match tls {
+        Some(_) => "https",
+        None => "http",
+    };
+    tracing::info!("listening on {}://{}", scheme, listener.local_addr()?);
+
+    let handle = Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            stop_requested(shutdown_delay).await;
+            tracing::info!(
+                "shutting down, waiting up to {}s for requests in flight",
+                drain_timeout.as_secs()
+            );
+            handle.graceful_shutdown(Some(drain_timeout));
+        }
+    });
+
+    let listener = listener.into_std()?;
+    let service = router.into_make_service();
+    match tls {
+        Some(tls) => {
+            axum_server::from_tcp_rustls(listener, tls)
+                .handle(handle)
+                .serve(service)
+                .await
+        }
+        None => {
+            axum_server::from_tcp(listener)
+                .handle(handle)
+                .serve(service)
+                .await
+        }
+    }
+    .context("the server failed")
+}
+
+/// `serve` for a Unix socket, which axum_server cannot accept from
+async fn serve_unix(
+    listener: &UnixListener,
+    path: &Path,
+    router: Router,
+    shutdown_delay: Duration,
+    drain_timeout: Duration,
+) -> anyhow::Result<()> {
+    tracing::info!("listening on unix:{}", path.display());
+
+    let graceful = GracefulShutdown::new();
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let signal = stop_requested(shutdown_delay);
+    tokio::pin!(signal);
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    // such as running out of file descriptors, which
+                    // passes once other connections close
+                    tracing::warn!("could not accept a connection: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = &mut signal => break,
+        };
+        let connection = builder
+            .serve_connection_with_upgrades(
+                TokioIo::new(stream),
+                TowerToHyperService::new(router.clone()),
+            )
+            .into_owned();
+        let connection = graceful.watch(connection);
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::debug!("connection failed: {}", e);
+            }
+        });
+    }
+
+    tracing::info!(
+        "shutting down, waiting up to {}s for requests in flight",
+        drain_timeout.as_secs()
+    );
+    if tokio::time::timeout(drain_timeout, graceful.shutdown())
+        .await
+        .is_err()
+    {
+        tracing::warn!("dropping the requests still in flight");
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct ProbeResponse {
+    pub status: &'static str,
+}
+
+/// `GET /livez`: the process is up and answering requests
+pub async fn livez_handler() -> Json<ProbeResponse> {
+    Json(ProbeResponse { status: "ok" })
+}
+
+/// Answer of `GET /readyz` given the outcome of the app's checks, `503`
+/// with the reason when one of them failed or the process is stopping
+pub fn readiness_response(checks: anyhow::Result<()>) -> Result<Json<ProbeResponse>, AppError> {
+    if SHUTTING_DOWN.load(Ordering::Relaxed) {
+        return Err(AppError {
+            err_msg: "not ready: shutting down".into(),
+            status_code: StatusCode::SERVICE_UNAVAILABLE,
+        });
+    }
+
+    match checks {
+        Ok(()) => Ok(Json(ProbeResponse { status: "ok" })),
+        Err(e) => Err(AppError {
+            err_msg: format!("not ready: {:#}", e),
+            status_code: StatusCode::SERVICE_UNAVAILABLE,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readiness_fails_once_shutting_down() {
+        assert!(readiness_response(Ok(())).is_ok());
+        let err = readiness_response(Err(anyhow::anyhow!("no database")))
+            .err()
+            .unwrap();
+        assert_eq!(err.status_code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(err.err_msg, "not ready: no database");
+
+        // as `shutdown_signal` does when the signal arrives
+        SHUTTING_DOWN.store(true, Ordering::Relaxed);
+        let err = readiness_response(Ok(())).err().unwrap();
+        assert_eq!(err.status_code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(err.err_msg, "not ready: shutting down");
+    }
+}
@@ -0,0 +1,86 @@
+use std::{io::IsTerminal, time::Duration};
+
+use axum::{extract::Request, response::Response, Router};
+use http::HeaderName;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::{field::Empty, Span};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+use crate::config::{LogFormat, LoggingConfig};
+
+/// Header with the id of a request. One is generated unless a proxy in
+/// front of the app already set it, and it is echoed in the response.
+const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Write log lines to stdout in the configured format. `RUST_LOG`, when
+/// set, replaces the configured level.
+pub fn init_tracing(config: &LoggingConfig) -> anyhow::Result<()> {
+    let filter = match std::env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(directives) => EnvFilter::try_new(directives)?,
+        Err(_) => EnvFilter::try_new(&config.level)?,
+    };
+    let registry = tracing_subscriber::registry().with(filter);
+
+    match config.format {
+        LogFormat::Pretty => registry
+            .with(fmt::layer().with_ansi(std::io::stdout().is_terminal()))
+            .try_init()?,
+        LogFormat::Json => registry
+            .with(
+                fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true),
+            )
+            .try_init()?,
+    }
+
+    Ok(())
+}
+
+/// Report the error that stopped an app, through the log once it is set
+/// up and on stderr before that
+pub fn report_fatal(error: &anyhow::Error) {
+    if tracing::dispatcher::has_been_set() {
+        tracing::error!(error = format!("{:#}", error), "exited with an error");
+    } else {
+        eprintln!("error: {:#}", error);
+    }
+}
+
+/// Run every request of `router` in a span with its method, path and
+/// request id, which the database spans of its handlers nest under. A
+/// line with the status and latency is logged once the response is ready.
+pub fn with_request_tracing(router: Router) -> Router {
+    let trace = TraceLayer::new_for_http()
+        .make_span_with(|request: &Request| {
+            let request_id = request
+                .headers()
+                .get(&REQUEST_ID_HEADER)
+                .and_then(|id| id.to_str().ok())
+                .unwrap_or_default();
+            tracing::info_span!(
+                "request",
+                method = %request.method(),
+                path = %request.uri().path(),
+                request_id,
+                status = Empty,
+                latency_ms = Empty,
+            )
+        })
+        .on_request(())
+        .on_response(|response: &Response, latency: Duration, span: &Span| {
+            span.record("status", response.status().as_u16());
+            span.record("latency_ms", latency.as_secs_f64() * 1000.0);
+            tracing::info!("finished processing request");
+        });
+
+    // The id is set outermost, so the span above already sees it
+    router
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(trace)
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+}
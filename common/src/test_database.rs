@@ -0,0 +1,15 @@
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ConnectOptions, DatabaseConnection};
+
+/// A fresh, migrated SQLite database in a file of the temp directory,
+/// which several connections can use at once. `name` keeps the tests
+/// apart.
+pub(crate) async fn connect(name: &str) -> DatabaseConnection {
+    let path = std::env::temp_dir().join(format!("cms-{}-{}.db", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut options = ConnectOptions::new(format!("sqlite://{}?mode=rwc", path.display()));
+    options.max_connections(8).sqlx_logging(false);
+    let db = sea_orm::Database::connect(options).await.unwrap();
+    Migrator::up(&db, None).await.unwrap();
+    db
+}
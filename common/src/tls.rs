@@ -0,0 +1,165 @@
+use std::{
+    fs,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::Request,
+    response::{IntoResponse, Response},
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use http::{header, uri::Authority, HeaderValue, StatusCode};
+use tower_http::set_header::SetResponseHeaderLayer;
+
+use crate::{bind_listener, CmsRustConfig};
+
+/// How often the certificate files are checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Modification times and sizes of the certificate and key, which change
+/// whenever either is renewed. `None` while one of them can't be read.
+fn files_stamp(cert: &str, key: &str) -> Option<[(SystemTime, u64); 2]> {
+    let stamp = |path: &str| {
+        let metadata = fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    };
+    Some([stamp(cert)?, stamp(key)?])
+}
+
+/// Read a PEM certificate chain and the private key that goes with it
+pub(crate) async fn read_certificate(cert: &str, key: &str) -> anyhow::Result<RustlsConfig> {
+    // Only the first call in a process installs the provider, later ones
+    // fail without consequence
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(cert, key)
+        .await
+        .with_context(|| {
+            format!(
+                "could not load the TLS certificate {} with key {}",
+                cert, key
+            )
+        })
+}
+
+/// Load the certificate and key named by the config, `None` when TLS is
+/// off. The files are watched from then on, so a renewed certificate is
+/// served without a restart.
+pub async fn load_tls(config: &CmsRustConfig) -> anyhow::Result<Option<RustlsConfig>> {
+    let (Some(cert), Some(key)) = (&config.tls_cert_path, &config.tls_key_path) else {
+        return Ok(None);
+    };
+
+    let tls = read_certificate(cert, key).await?;
+    spawn_cert_reloader(cert.clone(), key.clone(), tls.clone());
+    Ok(Some(tls))
+}
+
+/// Swap in the certificate whenever its files change. A certificate that
+/// fails to load, such as one whose key isn't written yet, leaves the
+/// current one in place until the next change.
+fn spawn_cert_reloader(cert: String, key: String, tls: RustlsConfig) {
+    tokio::spawn(async move {
+        let mut stamp = files_stamp(&cert, &key);
+        let mut ticker = tokio::time::interval(WATCH_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+            let current = files_stamp(&cert, &key);
+            if current.is_none() || current == stamp {
+                continue;
+            }
+            stamp = current;
+
+            match tls.reload_from_pem_file(&cert, &key).await {
+                Ok(()) => tracing::info!("reloaded the TLS certificate from {}", cert),
+                Err(e) => tracing::error!(
+                    "could not reload the TLS certificate, keeping the current one: {}",
+                    e
+                ),
+            }
+        }
+    });
+}
+
+/// The Strict-Transport-Security header, sent when HTTP requests are
+/// redirected to HTTPS so browsers skip plain HTTP altogether next time
+fn hsts_header(config: &CmsRustConfig) -> Option<HeaderValue> {
+    if config.tls_cert_path.is_none()
+        || config.http_redirect_port == 0
+        || config.hsts_max_age_secs == 0
+    {
+        return None;
+    }
+    HeaderValue::from_str(&format!("max-age={}", config.hsts_max_age_secs)).ok()
+}
+
+/// Add the Strict-Transport-Security header to the responses of `router`,
+/// when the config asks for it
+pub fn with_hsts(router: Router, config: &CmsRustConfig) -> Router {
+    match hsts_header(config) {
+        Some(hsts) => router.layer(SetResponseHeaderLayer::overriding(
+            header::STRICT_TRANSPORT_SECURITY,
+            hsts,
+        )),
+        None => router,
+    }
+}
+
+/// Answer a plain HTTP request with a `301` to the same host and path
+/// over HTTPS on `https_port`
+async fn redirect_to_https(request: Request, https_port: u16) -> Response {
+    let authority = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .and_then(|host| host.parse::<Authority>().ok())
+        .or_else(|| request.uri().authority().cloned());
+    let Some(authority) = authority else {
+        return (StatusCode::BAD_REQUEST, "missing Host header").into_response();
+    };
+
+    let host = match https_port {
+        443 => authority.host().to_string(),
+        port => format!("{}:{}", authority.host(), port),
+    };
+    let path = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+
+    Response::builder()
+        .status(StatusCode::MOVED_PERMANENTLY)
+        .header(header::LOCATION, format!("https://{}{}", host, path))
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// Redirect plain HTTP requests on `http_redirect_port` to HTTPS on
+/// `webserver_port`, in the background. Does nothing unless the config
+/// sets both TLS and the redirect port.
+pub async fn spawn_https_redirect(config: &CmsRustConfig) -> anyhow::Result<()> {
+    if config.tls_cert_path.is_none() || config.http_redirect_port == 0 {
+        return Ok(());
+    }
+
+    let listener = bind_listener(&config.webserver_bind, config.http_redirect_port)
+        .await
+        .context("could not start the HTTPS redirect listener")?;
+    let https_port = config.webserver_port;
+    let router =
+        Router::new().fallback(move |request: Request| redirect_to_https(request, https_port));
+    let router = with_hsts(router, config);
+
+    tracing::info!("redirecting http://{} to HTTPS", listener.local_addr()?);
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router).await {
+            tracing::error!("the HTTPS redirect listener stopped: {}", e);
+        }
+    });
+
+    Ok(())
+}
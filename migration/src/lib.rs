@@ -1,12 +1,24 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20241020_000002_add_post_timestamps;
+mod m20241021_000003_add_posts_fulltext_index;
+mod m20241022_000004_add_post_seo_fields;
+mod m20241023_000005_create_cache_generation;
+mod m20241024_000006_create_menus;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20241020_000002_add_post_timestamps::Migration),
+            Box::new(m20241021_000003_add_posts_fulltext_index::Migration),
+            Box::new(m20241022_000004_add_post_seo_fields::Migration),
+            Box::new(m20241023_000005_create_cache_generation::Migration),
+            Box::new(m20241024_000006_create_menus::Migration),
+        ]
     }
 }
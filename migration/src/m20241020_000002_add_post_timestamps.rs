@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite adds one column per statement, and only with a constant
+        // default. Tables created there are new and empty, so the default
+        // is never actually used.
+        let default = match manager.get_database_backend() {
+            sea_orm::DatabaseBackend::Sqlite => Expr::val("1970-01-01 00:00:00+00:00"),
+            _ => Expr::current_timestamp(),
+        };
+
+        for column in [Posts::CreatedAt, Posts::UpdatedAt] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Posts::Table)
+                        .add_column(
+                            ColumnDef::new(column)
+                                .timestamp_with_time_zone()
+                                .not_null()
+                                .default(default.clone()),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // Listings are sorted newest-first by default
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_posts_created_at")
+                    .table(Posts::Table)
+                    .col(Posts::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_posts_created_at")
+                    .table(Posts::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        for column in [Posts::CreatedAt, Posts::UpdatedAt] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Posts::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only MariaDB searches through a FULLTEXT index, the other
+        // backends match substrings instead
+        if manager.get_database_backend() != sea_orm::DatabaseBackend::MySql {
+            return Ok(());
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("ft_posts_search")
+                    .table(Posts::Table)
+                    .col(Posts::Title)
+                    .col(Posts::Excerpt)
+                    .col(Posts::Content)
+                    .full_text()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != sea_orm::DatabaseBackend::MySql {
+            return Ok(());
+        }
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("ft_posts_search")
+                    .table(Posts::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    Title,
+    Excerpt,
+    Content,
+}
@@ -0,0 +1,71 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            ColumnDef::new(Posts::MetaDescription)
+                .string()
+                .null()
+                .to_owned(),
+            ColumnDef::new(Posts::OgImage).string().null().to_owned(),
+            ColumnDef::new(Posts::CanonicalUrl)
+                .string()
+                .null()
+                .to_owned(),
+            ColumnDef::new(Posts::Noindex)
+                .boolean()
+                .not_null()
+                .default(false)
+                .to_owned(),
+        ];
+
+        // One column per statement, as SQLite does not take more
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Posts::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            Posts::MetaDescription,
+            Posts::OgImage,
+            Posts::CanonicalUrl,
+            Posts::Noindex,
+        ];
+
+        for column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Posts::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    MetaDescription,
+    OgImage,
+    CanonicalUrl,
+    Noindex,
+}
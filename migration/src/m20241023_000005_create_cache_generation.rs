@@ -0,0 +1,103 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CacheGeneration::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CacheGeneration::CacheKey)
+                            .string_len(64)
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CacheGeneration::Generation)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The public app polls for generations newer than the last one it saw
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_cache_generation_generation")
+                    .table(CacheGeneration::Table)
+                    .col(CacheGeneration::Generation)
+                    .to_owned(),
+            )
+            .await?;
+
+        // A single row, incremented by every write transaction. The row lock
+        // it takes makes concurrent writes get their generations one after
+        // the other, in the order they commit.
+        manager
+            .create_table(
+                Table::create()
+                    .table(CacheGenerationCounter::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CacheGenerationCounter::Id)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CacheGenerationCounter::Generation)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(CacheGenerationCounter::Table)
+                    .columns([
+                        CacheGenerationCounter::Id,
+                        CacheGenerationCounter::Generation,
+                    ])
+                    .values_panic([1.into(), 0.into()])
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(CacheGenerationCounter::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(CacheGeneration::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CacheGeneration {
+    Table,
+    CacheKey,
+    Generation,
+}
+
+#[derive(DeriveIden)]
+enum CacheGenerationCounter {
+    Table,
+    Id,
+    Generation,
+}
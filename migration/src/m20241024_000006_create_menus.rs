@@ -0,0 +1,129 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Menus::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Menus::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Menus::Name)
+                            .string_len(64)
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Deleting a menu or a parent item takes the items below it along.
+        // Deleting a linked post keeps the item, as a heading without a link.
+        manager
+            .create_table(
+                Table::create()
+                    .table(MenuItems::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MenuItems::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(MenuItems::MenuId).integer().not_null())
+                    .col(ColumnDef::new(MenuItems::ParentId).integer().null())
+                    .col(
+                        ColumnDef::new(MenuItems::Position)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(MenuItems::Label).string().not_null())
+                    .col(ColumnDef::new(MenuItems::Title).string().null())
+                    .col(ColumnDef::new(MenuItems::PostId).integer().null())
+                    .col(ColumnDef::new(MenuItems::Url).string().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_menu_items_menu")
+                            .from(MenuItems::Table, MenuItems::MenuId)
+                            .to(Menus::Table, Menus::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_menu_items_parent")
+                            .from(MenuItems::Table, MenuItems::ParentId)
+                            .to(MenuItems::Table, MenuItems::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_menu_items_post")
+                            .from(MenuItems::Table, MenuItems::PostId)
+                            .to(Posts::Table, Posts::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Menus are always read whole, in order
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_menu_items_menu_position")
+                    .table(MenuItems::Table)
+                    .col(MenuItems::MenuId)
+                    .col(MenuItems::Position)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MenuItems::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Menus::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Menus {
+    Table,
+    Id,
+    Name,
+}
+
+#[derive(DeriveIden)]
+enum MenuItems {
+    Table,
+    Id,
+    MenuId,
+    ParentId,
+    Position,
+    Label,
+    Title,
+    PostId,
+    Url,
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    Id,
+}